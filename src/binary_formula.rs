@@ -0,0 +1,407 @@
+use anyhow::{bail, Context, Result};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::exec::{self, CommandExt};
+use crate::forge::Release;
+use crate::formula;
+use crate::inputs::{Platform, PlatformArtifact};
+use crate::source_url;
+use crate::state;
+
+const ASSET_EXTENSIONS: &[&str] = &[".tar.gz", ".tgz", ".tar.xz", ".zip"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Os {
+    MacOs,
+    Linux,
+}
+
+impl Os {
+    pub fn block(self) -> &'static str {
+        match self {
+            Os::MacOs => "on_macos",
+            Os::Linux => "on_linux",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Arch {
+    Arm,
+    Intel,
+}
+
+impl Arch {
+    pub fn block(self) -> &'static str {
+        match self {
+            Arch::Arm => "on_arm",
+            Arch::Intel => "on_intel",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlatformAsset {
+    pub os: Os,
+    pub arch: Arch,
+    pub url: String,
+    pub sha256: String,
+}
+
+/// A formula that installs a prebuilt binary per platform.
+#[derive(Debug, Clone)]
+pub struct BinaryFormula {
+    pub class_name: String,
+    pub homepage: String,
+    pub version: String,
+    pub binary: String,
+    pub assets: Vec<PlatformAsset>,
+}
+
+impl BinaryFormula {
+    /// Builds a formula from a release's `<tool>_<ver>_<os>_<arch>` archives,
+    /// taking their sha256 from the release's checksums file.
+    pub fn from_release(
+        release: &Release,
+        repo: &str,
+        formula_name: &str,
+        checksums: &str,
+    ) -> Result<Self> {
+        let sums = parse_checksums(checksums);
+        let mut assets: Vec<PlatformAsset> = Vec::new();
+        let mut binary: Option<String> = None;
+
+        for asset in &release.assets {
+            let Some(platforms) = match_platforms(&asset.name) else {
+                continue;
+            };
+            let Some(sha256) = sums.get(&asset.name) else {
+                bail!("checksums file has no entry for {}", asset.name);
+            };
+            if binary.is_none() {
+                binary = tool_name(&asset.name);
+            }
+
+            for (os, arch) in platforms {
+                if assets
+                    .iter()
+                    .any(|found| found.os == os && found.arch == arch)
+                {
+                    continue;
+                }
+                assets.push(PlatformAsset {
+                    os,
+                    arch,
+                    url: asset.url.clone(),
+                    sha256: sha256.clone(),
+                });
+            }
+        }
+
+        if assets.is_empty() {
+            bail!(
+                "release {} of {} has no macOS or Linux archives named like <tool>_<version>_<os>_<arch>.tar.gz",
+                release.tag_name,
+                repo
+            );
+        }
+
+        let repo_name = repo.rsplit('/').next().unwrap_or(repo);
+        let version = source_url::version_from_tag(&release.tag_name, repo_name)
+            .unwrap_or_else(|| release.tag_name.trim_start_matches('v').to_string());
+        let binary = binary.unwrap_or_else(|| repo_name.to_string());
+
+        Ok(Self {
+            class_name: formula::class_name(formula_name),
+            homepage: format!("https://github.com/{repo}"),
+            version,
+            binary,
+            assets,
+        })
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "class {} < Formula", self.class_name);
+        let _ = writeln!(out, "  desc \"TODO: add a short description\"");
+        let _ = writeln!(out, "  homepage \"{}\"", self.homepage);
+        let _ = writeln!(out, "  version \"{}\"", self.version);
+
+        out.push_str(&render_platforms(&self.assets));
+
+        if let Some(block) = self
+            .assets
+            .first()
+            .and_then(|asset| formula::livecheck_block(&asset.url))
+        {
+            out.push('\n');
+            out.push_str(&block);
+        }
+
+        let _ = writeln!(out, "\n  def install");
+        let _ = writeln!(out, "    bin.install \"{}\"", self.binary);
+        let _ = writeln!(out, "  end");
+        let _ = writeln!(out, "\n  test do");
+        let _ = writeln!(
+            out,
+            "    assert_match version.to_s, shell_output(\"#{{bin}}/{} --version\")",
+            self.binary
+        );
+        let _ = writeln!(out, "  end");
+        out.push_str("end\n");
+        out
+    }
+}
+
+/// Renders `on_macos`/`on_linux` blocks, each holding `on_arm`/`on_intel`
+/// blocks with the asset's `url` and `sha256`. Every block starts with a blank
+/// line.
+pub fn render_platforms(assets: &[PlatformAsset]) -> String {
+    let mut assets = assets.to_vec();
+    assets.sort_by_key(|asset| (asset.os, asset.arch));
+
+    let mut out = String::new();
+    for os in [Os::MacOs, Os::Linux] {
+        let for_os: Vec<&PlatformAsset> = assets.iter().filter(|asset| asset.os == os).collect();
+        if for_os.is_empty() {
+            continue;
+        }
+
+        let _ = writeln!(out, "\n  {} do", os.block());
+        for (index, asset) in for_os.iter().enumerate() {
+            if index > 0 {
+                out.push('\n');
+            }
+            let _ = writeln!(out, "    {} do", asset.arch.block());
+            let _ = writeln!(out, "      url \"{}\"", asset.url);
+            let _ = writeln!(out, "      sha256 \"{}\"", asset.sha256);
+            let _ = writeln!(out, "    end");
+        }
+        let _ = writeln!(out, "  end");
+    }
+    out
+}
+
+/// Downloads each artifact and pairs it with its sha256.
+pub fn resolve_artifacts(
+    artifacts: &[PlatformArtifact],
+    downloads: &Downloads,
+) -> Result<Vec<PlatformAsset>> {
+    artifacts
+        .iter()
+        .map(|artifact| {
+            let (os, arch) = platform_parts(artifact.platform);
+            Ok(PlatformAsset {
+                os,
+                arch,
+                url: artifact.url.clone(),
+                sha256: downloads.sha256(&artifact.url)?,
+            })
+        })
+        .collect()
+}
+
+pub fn platform_parts(platform: Platform) -> (Os, Arch) {
+    match platform {
+        Platform::MacosArm64 => (Os::MacOs, Arch::Arm),
+        Platform::MacosX86_64 => (Os::MacOs, Arch::Intel),
+        Platform::LinuxArm64 => (Os::Linux, Arch::Arm),
+        Platform::LinuxX86_64 => (Os::Linux, Arch::Intel),
+    }
+}
+
+/// Archives downloaded for one run, kept in its run directory so that `plan`
+/// and the apply that checks it (or a resumed run) hash and inspect the same
+/// bytes without fetching them again.
+pub struct Downloads {
+    dir: PathBuf,
+}
+
+impl Downloads {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// The contents of `url`, downloaded on first use.
+    pub fn get(&self, url: &str) -> Result<Vec<u8>> {
+        let path = self
+            .dir
+            .join(format!("{:x}", Sha256::digest(url.as_bytes())));
+        if let Ok(data) = fs::read(&path) {
+            return Ok(data);
+        }
+
+        println!("    downloading {}", url);
+        let data = download(url)?;
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("failed to create {}", self.dir.display()))?;
+        state::write_atomic(&path, &data)?;
+        Ok(data)
+    }
+
+    pub fn sha256(&self, url: &str) -> Result<String> {
+        Ok(format!("{:x}", Sha256::digest(self.get(url)?)))
+    }
+}
+
+/// Downloads `url` into memory.
+pub fn download(url: &str) -> Result<Vec<u8>> {
+    let output = Command::new("curl")
+        .args(["--silent", "--show-error", "--location", "--fail", url])
+        .output_within(exec::APPLY_TIMEOUT)
+        .context("failed to run curl")?;
+    if !output.status.success() {
+        bail!(
+            "downloading {} failed: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(output.stdout)
+}
+
+/// The platforms an archive is built for, from names like
+/// `tool_1.0_darwin_arm64.tar.gz`; `None` for anything else (Windows builds,
+/// signatures, SBOMs, ...).
+pub fn match_platforms(name: &str) -> Option<Vec<(Os, Arch)>> {
+    let lower = name.to_ascii_lowercase();
+    let stem = ASSET_EXTENSIONS
+        .iter()
+        .find_map(|ext| lower.strip_suffix(ext))?;
+    let tokens: Vec<&str> = stem.split(['_', '-', '.']).collect();
+    let has = |words: &[&str]| tokens.iter().any(|token| words.contains(token));
+
+    let os = if has(&["darwin", "macos", "mac", "osx", "apple"]) {
+        Os::MacOs
+    } else if has(&["linux"]) {
+        Os::Linux
+    } else {
+        return None;
+    };
+
+    if stem.contains("arm64") || stem.contains("aarch64") {
+        Some(vec![(os, Arch::Arm)])
+    } else if stem.contains("amd64") || stem.contains("x86_64") || has(&["x64"]) {
+        Some(vec![(os, Arch::Intel)])
+    } else if os == Os::MacOs && has(&["universal", "all"]) {
+        Some(vec![(os, Arch::Arm), (os, Arch::Intel)])
+    } else {
+        None
+    }
+}
+
+/// The `<tool>` of a `<tool>_<version>_<os>_<arch>` archive name, which is
+/// the binary the archive holds.
+fn tool_name(name: &str) -> Option<String> {
+    let lower = name.to_ascii_lowercase();
+    let stem = ASSET_EXTENSIONS
+        .iter()
+        .find(|ext| lower.ends_with(*ext))
+        .map_or(name, |ext| &name[..name.len() - ext.len()]);
+    match source_url::split_name_version(stem) {
+        (tool, Some(_)) if !tool.is_empty() => Some(tool.to_string()),
+        _ => None,
+    }
+}
+
+/// Picks the checksums asset: `checksums.txt`, `<tool>_<ver>_checksums.txt`,
+/// `SHA256SUMS`, ...
+pub fn checksums_asset(release: &Release) -> Option<&str> {
+    release
+        .assets
+        .iter()
+        .map(|asset| asset.name.as_str())
+        .find(|name| {
+            let lower = name.to_ascii_lowercase();
+            lower.ends_with("checksums.txt") || lower == "sha256sums" || lower == "sha256sums.txt"
+        })
+}
+
+/// Parses `sha256sum` output: `<hex>  <file>` (or `<hex> *<file>`).
+pub fn parse_checksums(text: &str) -> HashMap<String, String> {
+    text.lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let sum = parts.next()?;
+            let file = parts.next()?.trim_start_matches('*');
+            let valid = sum.len() == 64 && sum.chars().all(|ch| ch.is_ascii_hexdigit());
+            valid.then(|| (file.to_string(), sum.to_ascii_lowercase()))
+        })
+        .collect()
+}
+
+/// The formula name for a release of `owner/repo`: the repository name,
+/// lowercase, with '_' as '-'.
+pub fn release_formula_name(repo: &str) -> String {
+    let name = repo.rsplit('/').next().unwrap_or(repo);
+    name.to_ascii_lowercase().replace('_', "-")
+}
+
+/// Reads `owner/repo` and an optional tag from `https://github.com/<owner>/<repo>`,
+/// `.../releases/tag/<tag>` or the `<owner>/<repo>[@<tag>]` shorthand.
+pub fn release_target(value: &str) -> Result<(String, Option<String>)> {
+    let value = value.trim().trim_end_matches('/');
+    let path = value
+        .strip_prefix("https://github.com/")
+        .or_else(|| value.strip_prefix("http://github.com/"))
+        .unwrap_or(value);
+    let segments: Vec<&str> = path.split('/').filter(|part| !part.is_empty()).collect();
+
+    match segments.as_slice() {
+        [owner, repo, "releases", "tag", tag] => {
+            Ok((format!("{owner}/{repo}"), Some(tag.to_string())))
+        }
+        [owner, repo] | [owner, repo, "releases"] | [owner, repo, "releases", "latest"] => {
+            match repo.split_once('@') {
+                Some((repo, tag)) => Ok((format!("{owner}/{repo}"), Some(tag.to_string()))),
+                None => Ok((format!("{owner}/{repo}"), None)),
+            }
+        }
+        _ => bail!(
+            "formula-url '{}' is not a GitHub repository or release (expected https://github.com/<owner>/<repo>[/releases/tag/<tag>])",
+            value
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forge::ReleaseAsset;
+
+    #[test]
+    fn installs_the_tool_named_by_the_assets() {
+        let asset = |name: &str| ReleaseAsset {
+            name: name.to_string(),
+            url: format!("https://github.com/acme/foo-cli/releases/download/v1.2.0/{name}"),
+        };
+        let release = Release {
+            tag_name: "v1.2.0".to_string(),
+            assets: vec![
+                asset("foo_1.2.0_darwin_arm64.tar.gz"),
+                asset("foo_1.2.0_linux_amd64.tar.gz"),
+                asset("checksums.txt"),
+            ],
+        };
+        let checksums = format!(
+            "{}  foo_1.2.0_darwin_arm64.tar.gz\n{}  foo_1.2.0_linux_amd64.tar.gz\n",
+            "a".repeat(64),
+            "b".repeat(64)
+        );
+
+        let formula =
+            BinaryFormula::from_release(&release, "acme/foo-cli", "foo-cli", &checksums).unwrap();
+        assert_eq!(formula.binary, "foo");
+        assert_eq!(formula.version, "1.2.0");
+        let rendered = formula.render();
+        assert!(rendered.contains("class FooCli < Formula"));
+        assert!(rendered.contains("bin.install \"foo\""));
+        assert!(rendered.contains("shell_output(\"#{bin}/foo --version\")"));
+    }
+}
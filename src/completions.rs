@@ -0,0 +1,148 @@
+use anyhow::{bail, Result};
+use std::path::{Component, Path};
+
+use crate::inputs::Completions;
+
+/// Checks that `--manpage` is a relative path to a page with a section
+/// suffix (`man/mytool.1`, `docs/mytool.5.gz`).
+pub fn normalize_manpage(path: String) -> Result<String> {
+    let path = path.trim().trim_start_matches("./").to_string();
+    let relative = Path::new(&path)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    if path.is_empty() || !relative || path.contains(['"', '\\', '#']) {
+        bail!(
+            "--manpage must be a plain path relative to the source tree (e.g. man/mytool.1), got '{}'",
+            path
+        );
+    }
+    if man_section(&path).is_none() {
+        bail!(
+            "--manpage {} does not end in a man section (.1 to .8)",
+            path
+        );
+    }
+    Ok(path)
+}
+
+fn man_section(path: &str) -> Option<char> {
+    let name = path.trim_end_matches(".gz");
+    let (_, section) = name.rsplit_once('.')?;
+    let mut chars = section.chars();
+    match (chars.next(), chars.next()) {
+        (Some(section @ '1'..='8'), None) => Some(section),
+        _ => None,
+    }
+}
+
+/// The `generate_completions_from_executable` call for `binary`.
+fn generate_line(completions: Completions, binary: &str) -> Option<String> {
+    match completions {
+        Completions::None => None,
+        // clap_complete's dynamic completions, driven by `COMPLETE=<shell>`.
+        Completions::Clap => Some(format!(
+            "generate_completions_from_executable(bin/\"{binary}\", shell_parameter_format: :clap)"
+        )),
+        // cobra's `completion <shell>` subcommand.
+        Completions::Cobra => Some(format!(
+            "generate_completions_from_executable(bin/\"{binary}\", \"completion\")"
+        )),
+    }
+}
+
+fn manpage_line(path: &str) -> Option<String> {
+    man_section(path).map(|section| format!("man{section}.install \"{path}\""))
+}
+
+/// A `test do` assertion that the bash completion script is not empty.
+fn test_line(completions: Completions, binary: &str) -> Option<String> {
+    match completions {
+        Completions::None => None,
+        Completions::Clap => Some(format!(
+            "refute_empty shell_output(\"COMPLETE=bash #{{bin}}/{binary}\")"
+        )),
+        Completions::Cobra => Some(format!(
+            "refute_empty shell_output(\"#{{bin}}/{binary} completion bash\")"
+        )),
+    }
+}
+
+fn has_generate_line(source: &str) -> bool {
+    source.lines().any(|line| {
+        line.trim_start()
+            .starts_with("generate_completions_from_executable(")
+    })
+}
+
+fn has_line(source: &str, expected: &str) -> bool {
+    source.lines().any(|line| line.trim() == expected)
+}
+
+/// Adds the completion and man page lines to the end of `def install`, and
+/// the completion assertion to `test do` (replacing its TODO placeholder).
+/// Lines the formula already has are not added again.
+pub fn set_stanzas(
+    source: &str,
+    completions: Completions,
+    manpage: Option<&str>,
+    binary: &str,
+) -> String {
+    let install = missing(source, completions, manpage, binary);
+    let test = missing_test(source, completions, binary);
+    if install.is_empty() && test.is_none() {
+        return source.to_string();
+    }
+
+    let mut out = String::new();
+    let mut section: Option<&str> = None;
+    for line in source.lines() {
+        match line {
+            "  def install" | "  test do" => section = Some(line),
+            "  end" => {
+                let lines = match section.take() {
+                    Some("  def install") => &install[..],
+                    Some(_) => test.as_slice(),
+                    None => &[],
+                };
+                for added in lines {
+                    out.push_str(&format!("    {added}\n"));
+                }
+            }
+            // The stub's placeholder test gives way to a real one.
+            "    # TODO: add a test" if section == Some("  test do") && test.is_some() => {
+                continue;
+            }
+            _ => {}
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+/// The `test do` line `set_stanzas` would add, unless `source` has it.
+pub fn missing_test(source: &str, completions: Completions, binary: &str) -> Option<String> {
+    test_line(completions, binary).filter(|line| !has_line(source, line))
+}
+
+/// The stanzas `set_stanzas` would add that `source` does not have.
+pub fn missing(
+    source: &str,
+    completions: Completions,
+    manpage: Option<&str>,
+    binary: &str,
+) -> Vec<String> {
+    let mut missing = Vec::new();
+    if let Some(line) = generate_line(completions, binary) {
+        if !has_generate_line(source) {
+            missing.push(line);
+        }
+    }
+    if let Some(line) = manpage
+        .and_then(manpage_line)
+        .filter(|line| !has_line(source, line))
+    {
+        missing.push(line);
+    }
+    missing
+}
@@ -0,0 +1,35 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+use crate::dependency::Dependency;
+use crate::inputs::{Completions, TestStrategy};
+use crate::license::License;
+
+/// Formula options read from `--config <file>` (JSON). Keys are named after
+/// the flags they stand in for; flags add to lists and replace single values.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    pub depends_on: Vec<Dependency>,
+    pub service_run: Option<String>,
+    pub service_keep_alive: Option<bool>,
+    pub service_log_path: Option<String>,
+    pub caveats: Option<String>,
+    pub completions: Option<Completions>,
+    pub manpage: Option<String>,
+    pub test_strategy: Option<TestStrategy>,
+    pub test_command: Option<String>,
+    pub test_expect: Option<String>,
+    pub license: Option<License>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read config: {}", path.display()))?;
+        serde_json::from_str(&text)
+            .with_context(|| format!("failed to parse config: {}", path.display()))
+    }
+}
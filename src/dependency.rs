@@ -0,0 +1,484 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use crate::exec::{self, CommandExt};
+use crate::validate;
+
+const FORMULAE_CACHE: &str = "brew-formulae.txt";
+const FORMULAE_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// macOS releases Homebrew accepts in `depends_on macos:`.
+const MACOS_RELEASES: &[&str] = &[
+    "el_capitan",
+    "sierra",
+    "high_sierra",
+    "mojave",
+    "catalina",
+    "big_sur",
+    "monterey",
+    "ventura",
+    "sonoma",
+    "sequoia",
+    "tahoe",
+];
+
+/// `=> :build` and friends. Declaration order is Homebrew's canonical order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Tag {
+    Build,
+    Test,
+    Runtime,
+    Recommended,
+    Optional,
+}
+
+impl Tag {
+    fn symbol(self) -> Option<&'static str> {
+        match self {
+            Tag::Build => Some("build"),
+            Tag::Test => Some("test"),
+            Tag::Runtime => None,
+            Tag::Recommended => Some("recommended"),
+            Tag::Optional => Some("optional"),
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "build" => Some(Tag::Build),
+            "test" => Some(Tag::Test),
+            "recommended" => Some(Tag::Recommended),
+            "optional" => Some(Tag::Optional),
+            _ => None,
+        }
+    }
+}
+
+/// What a `depends_on` line requires. Requirements sort before formulae.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Target {
+    Xcode(Option<String>),
+    MacOs(String),
+    Linux,
+    Arch(String),
+    Formula(String),
+}
+
+impl Target {
+    /// Whether two lines would declare the same thing: the same formula, or
+    /// the same kind of requirement.
+    fn conflicts(&self, other: &Target) -> bool {
+        match (self, other) {
+            (Target::Formula(a), Target::Formula(b)) => a == b,
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+}
+
+/// One `depends_on` line, written on the command line as `name[:tag]`,
+/// `macos:<release>`, `linux`, `arch:<arm64|x86_64>` or
+/// `xcode[:<version>][:build]`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Dependency {
+    pub target: Target,
+    pub tag: Tag,
+}
+
+impl Dependency {
+    /// The Ruby stanza, e.g. `depends_on "rust" => :build`.
+    pub fn stanza(&self) -> String {
+        let tag = self.tag.symbol();
+        match (&self.target, tag) {
+            (Target::Formula(name), Some(tag)) => format!("depends_on \"{name}\" => :{tag}"),
+            (Target::Formula(name), None) => format!("depends_on \"{name}\""),
+            (Target::MacOs(release), _) => format!("depends_on macos: :{release}"),
+            (Target::Linux, _) => "depends_on :linux".to_string(),
+            (Target::Arch(arch), _) => format!("depends_on arch: :{arch}"),
+            (Target::Xcode(None), None) => "depends_on :xcode".to_string(),
+            (Target::Xcode(None), Some(tag)) => format!("depends_on xcode: :{tag}"),
+            (Target::Xcode(Some(version)), None) => format!("depends_on xcode: \"{version}\""),
+            (Target::Xcode(Some(version)), Some(tag)) => {
+                format!("depends_on xcode: [\"{version}\", :{tag}]")
+            }
+        }
+    }
+
+    /// Reads a stanza written by `stanza` (or by `brew create`) back.
+    pub fn from_stanza(line: &str) -> Option<Self> {
+        let rest = line.trim().strip_prefix("depends_on ")?.trim();
+
+        if let Some(quoted) = rest.strip_prefix('"') {
+            let (name, after) = quoted.split_once('"')?;
+            let after = after.trim();
+            let tag = match after.strip_prefix("=>") {
+                Some(tag) => Tag::parse(tag.trim().strip_prefix(':')?)?,
+                None if after.is_empty() => Tag::Runtime,
+                None => return None,
+            };
+            return Some(Self {
+                target: Target::Formula(name.to_string()),
+                tag,
+            });
+        }
+
+        let symbol = |value: &str| value.trim().strip_prefix(':').map(str::to_string);
+        let (target, tag) = match rest {
+            ":linux" => (Target::Linux, Tag::Runtime),
+            ":xcode" => (Target::Xcode(None), Tag::Runtime),
+            _ => {
+                let (key, value) = rest.split_once(':')?;
+                let value = value.trim();
+                match key {
+                    "macos" => (Target::MacOs(symbol(value)?), Tag::Runtime),
+                    "arch" => (Target::Arch(symbol(value)?), Tag::Runtime),
+                    "xcode" => {
+                        let items: Vec<&str> = value
+                            .trim_start_matches('[')
+                            .trim_end_matches(']')
+                            .split(',')
+                            .map(str::trim)
+                            .collect();
+                        let version = items
+                            .iter()
+                            .find_map(|item| item.strip_prefix('"')?.strip_suffix('"'))
+                            .map(str::to_string);
+                        let tag = match items.iter().find_map(|item| symbol(item)) {
+                            Some(tag) => Tag::parse(&tag)?,
+                            None => Tag::Runtime,
+                        };
+                        (Target::Xcode(version), tag)
+                    }
+                    _ => return None,
+                }
+            }
+        };
+        Some(Self { target, tag })
+    }
+
+    fn sort_key(&self) -> (Tag, &Target) {
+        (self.tag, &self.target)
+    }
+}
+
+impl FromStr for Dependency {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let mut parts = value.split(':');
+        let name = parts.next().unwrap_or_default();
+        let rest: Vec<&str> = parts.collect();
+        let tag = |part: Option<&&str>| -> Result<Tag, String> {
+            match part {
+                None => Ok(Tag::Runtime),
+                Some(part) => Tag::parse(part).ok_or_else(|| {
+                    format!(
+                        "unknown dependency type ':{part}' in '{value}'; expected build, test, optional or recommended"
+                    )
+                }),
+            }
+        };
+
+        let (target, tag) = match (name, rest.as_slice()) {
+            ("macos", [release]) => {
+                if !MACOS_RELEASES.contains(release) {
+                    return Err(format!(
+                        "unknown macOS release '{release}'; expected one of {}",
+                        MACOS_RELEASES.join(", ")
+                    ));
+                }
+                (Target::MacOs(release.to_string()), Tag::Runtime)
+            }
+            ("linux", []) => (Target::Linux, Tag::Runtime),
+            ("arch", [arch @ ("arm64" | "x86_64")]) => (Target::Arch(arch.to_string()), Tag::Runtime),
+            ("xcode", []) => (Target::Xcode(None), Tag::Runtime),
+            ("xcode", [first]) if first.starts_with(|ch: char| ch.is_ascii_digit()) => {
+                (Target::Xcode(Some(first.to_string())), Tag::Runtime)
+            }
+            ("xcode", [first]) => (Target::Xcode(None), tag(Some(first))?),
+            ("xcode", [version, kind]) => {
+                (Target::Xcode(Some(version.to_string())), tag(Some(kind))?)
+            }
+            ("macos" | "linux" | "arch" | "xcode", _) => {
+                return Err(format!(
+                    "'{value}' is not a valid requirement; use macos:<release>, linux, arch:<arm64|x86_64> or xcode[:<version>][:build]"
+                ))
+            }
+            (name, [] | [_]) => {
+                // Tap formulae are `owner/tap/name`; only the name follows formula rules.
+                let formula = name.rsplit('/').next().unwrap_or(name);
+                validate::formula_name(formula).map_err(|err| format!("{err:#}"))?;
+                if name.split('/').count() != 1 && name.split('/').count() != 3 {
+                    return Err(format!(
+                        "dependency '{name}' must be a formula name or <owner>/<tap>/<name>"
+                    ));
+                }
+                (Target::Formula(name.to_string()), tag(rest.first())?)
+            }
+            _ => return Err(format!("expected <name>[:<type>], got '{value}'")),
+        };
+
+        Ok(Self { target, tag })
+    }
+}
+
+impl TryFrom<String> for Dependency {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Dependency> for String {
+    fn from(dependency: Dependency) -> Self {
+        dependency.to_string()
+    }
+}
+
+impl fmt::Display for Dependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tag = self.tag.symbol();
+        match &self.target {
+            Target::Formula(name) => write!(f, "{name}")?,
+            Target::MacOs(release) => return write!(f, "macos:{release}"),
+            Target::Linux => return write!(f, "linux"),
+            Target::Arch(arch) => return write!(f, "arch:{arch}"),
+            Target::Xcode(None) => write!(f, "xcode")?,
+            Target::Xcode(Some(version)) => write!(f, "xcode:{version}")?,
+        }
+        match tag {
+            Some(tag) => write!(f, ":{tag}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Sorts into Homebrew's canonical order (build, test, runtime,
+/// recommended, optional; requirements before formulae, then by name) and
+/// rejects the same target given twice.
+pub fn normalize(mut dependencies: Vec<Dependency>) -> Result<Vec<Dependency>> {
+    dependencies.sort_by(|a, b| a.sort_key().cmp(&b.sort_key()));
+    let mut seen: Vec<&Target> = Vec::new();
+    for dependency in &dependencies {
+        if seen
+            .iter()
+            .any(|target| target.conflicts(&dependency.target))
+        {
+            bail!(
+                "--depends-on lists {} more than once",
+                Dependency {
+                    target: dependency.target.clone(),
+                    tag: Tag::Runtime,
+                }
+            );
+        }
+        seen.push(&dependency.target);
+    }
+    Ok(dependencies)
+}
+
+/// Writes `dependencies` into a formula, merged with the top-level
+/// `depends_on` lines it already has (an input replaces an existing line for
+/// the same target), in canonical order. New lines go before the platform
+/// blocks, resources and `def install`.
+pub fn set_dependencies(source: &str, dependencies: &[Dependency]) -> String {
+    let mut existing: Vec<Dependency> = Vec::new();
+    let mut unparsed: Vec<&str> = Vec::new();
+    let mut kept: Vec<&str> = Vec::new();
+    let mut insert_at: Option<usize> = None;
+
+    for line in source.lines() {
+        if line.starts_with("  depends_on ") {
+            insert_at.get_or_insert(kept.len());
+            match Dependency::from_stanza(line) {
+                Some(dependency) => existing.push(dependency),
+                None => unparsed.push(line),
+            }
+            continue;
+        }
+        kept.push(line);
+    }
+
+    if dependencies.is_empty() && insert_at.is_none() {
+        return source.to_string();
+    }
+
+    let mut merged: Vec<Dependency> = existing
+        .into_iter()
+        .filter(|found| {
+            !dependencies
+                .iter()
+                .any(|dependency| dependency.target.conflicts(&found.target))
+        })
+        .collect();
+    merged.extend(dependencies.iter().cloned());
+    merged.sort_by(|a, b| a.sort_key().cmp(&b.sort_key()));
+
+    let mut block: Vec<String> = merged
+        .iter()
+        .map(|dependency| format!("  {}", dependency.stanza()))
+        .collect();
+    block.extend(unparsed.iter().map(|line| line.to_string()));
+
+    let insert_at = match insert_at {
+        Some(index) => index,
+        None => {
+            let anchors = [
+                "  uses_from_macos ",
+                "  on_macos do",
+                "  on_linux do",
+                "  on_arm do",
+                "  on_intel do",
+                "  resource \"",
+                "  patch",
+                "  def install",
+            ];
+            let anchor = kept
+                .iter()
+                .position(|line| anchors.iter().any(|anchor| line.starts_with(anchor)))
+                .or_else(|| kept.iter().rposition(|line| *line == "end"))
+                .unwrap_or(kept.len());
+            // Keep the blank line that separates the anchor from what precedes it.
+            let before_blank = match anchor.checked_sub(1) {
+                Some(before) if kept[before].trim().is_empty() => before,
+                _ => anchor,
+            };
+            block.insert(0, String::new());
+            if before_blank == anchor {
+                block.push(String::new());
+            }
+            before_blank
+        }
+    };
+
+    let mut out = String::new();
+    for line in &kept[..insert_at] {
+        out.push_str(line);
+        out.push('\n');
+    }
+    for line in &block {
+        out.push_str(line);
+        out.push('\n');
+    }
+    for line in &kept[insert_at..] {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+/// Every formula a top-level `depends_on` line of `source` declares.
+pub fn declared(source: &str) -> Vec<Dependency> {
+    source
+        .lines()
+        .filter(|line| line.starts_with("  depends_on "))
+        .filter_map(Dependency::from_stanza)
+        .collect()
+}
+
+/// Fails when a formula dependency is not in `brew formulae`. The list is
+/// cached under `cache_dir` for a day; without brew nothing is checked.
+/// Tap formulae (`owner/tap/name`) are not checked.
+pub fn check_known(dependencies: &[Dependency], cache_dir: &Path) -> Result<()> {
+    let names: Vec<&str> = dependencies
+        .iter()
+        .filter_map(|dependency| match &dependency.target {
+            Target::Formula(name) if !name.contains('/') => Some(name.as_str()),
+            _ => None,
+        })
+        .collect();
+    if names.is_empty() {
+        return Ok(());
+    }
+
+    let Some(known) = brew_formulae(cache_dir)? else {
+        println!("    brew not available; dependency names not checked");
+        return Ok(());
+    };
+
+    let unknown: Vec<String> = names
+        .iter()
+        .filter(|name| !known.contains(**name))
+        .map(|name| match closest(name, &known) {
+            Some(suggestion) => format!("{name} (did you mean {suggestion}?)"),
+            None => name.to_string(),
+        })
+        .collect();
+    if !unknown.is_empty() {
+        bail!(
+            "unknown dependencies (not in `brew formulae`): {}",
+            unknown.join(", ")
+        );
+    }
+    Ok(())
+}
+
+fn brew_formulae(cache_dir: &Path) -> Result<Option<HashSet<String>>> {
+    let cache = cache_dir.join(FORMULAE_CACHE);
+    let fresh = fs::metadata(&cache)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age < FORMULAE_CACHE_TTL);
+
+    if !fresh {
+        let output = match Command::new("brew")
+            .arg("formulae")
+            .output_within(exec::QUERY_TIMEOUT)
+        {
+            Ok(output) if output.status.success() => output,
+            _ if cache.exists() => return read_formulae(&cache).map(Some),
+            _ => return Ok(None),
+        };
+        fs::create_dir_all(cache_dir)
+            .with_context(|| format!("failed to create {}", cache_dir.display()))?;
+        fs::write(&cache, &output.stdout)
+            .with_context(|| format!("failed to write {}", cache.display()))?;
+    }
+
+    read_formulae(&cache).map(Some)
+}
+
+fn read_formulae(cache: &Path) -> Result<HashSet<String>> {
+    let text =
+        fs::read_to_string(cache).with_context(|| format!("failed to read {}", cache.display()))?;
+    Ok(text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// The known name within edit distance 2, if any.
+fn closest<'a>(name: &str, known: &'a HashSet<String>) -> Option<&'a str> {
+    known
+        .iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= 2)
+        .min()
+        .map(|(_, candidate)| candidate.as_str())
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
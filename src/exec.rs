@@ -0,0 +1,279 @@
+use anyhow::{Context, Result};
+use std::fmt;
+use std::io::{Read, Write};
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::interrupt::{self, Interrupted};
+use crate::state::RunContext;
+
+/// Timeout for quick, read-only queries (`git status`, `gh repo view`, ...).
+pub const QUERY_TIMEOUT: Duration = Duration::from_secs(60);
+/// Timeout for commands that change things or talk to the network at length.
+pub const APPLY_TIMEOUT: Duration = Duration::from_secs(600);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// How long an interrupted child gets to exit before it is killed.
+const INTERRUPT_GRACE: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    pub const fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            initial_backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+        }
+    }
+
+    pub const fn network() -> Self {
+        Self {
+            max_attempts: 4,
+            initial_backoff: Duration::from_secs(2),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// Returned when a child process outlives its wall-clock timeout and is killed.
+#[derive(Debug)]
+pub struct CommandTimeout {
+    pub command: String,
+    pub timeout: Duration,
+}
+
+impl fmt::Display for CommandTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} timed out after {}s and was killed",
+            self.command,
+            self.timeout.as_secs()
+        )
+    }
+}
+
+impl std::error::Error for CommandTimeout {}
+
+pub fn describe(cmd: &Command) -> String {
+    let mut parts = vec![cmd.get_program().to_string_lossy().to_string()];
+    parts.extend(cmd.get_args().map(|arg| arg.to_string_lossy().to_string()));
+    parts.join(" ")
+}
+
+pub trait CommandExt {
+    /// Runs the command, capturing stdout and stderr, and kills it after `timeout`.
+    fn output_within(&mut self, timeout: Duration) -> Result<Output>;
+
+    /// Like `output_within`, but echoes the child's output while it runs.
+    fn stream_within(&mut self, timeout: Duration) -> Result<Output>;
+}
+
+impl CommandExt for Command {
+    fn output_within(&mut self, timeout: Duration) -> Result<Output> {
+        run(self, timeout, false)
+    }
+
+    fn stream_within(&mut self, timeout: Duration) -> Result<Output> {
+        run(self, timeout, true)
+    }
+}
+
+fn run(cmd: &mut Command, timeout: Duration, echo: bool) -> Result<Output> {
+    interrupt::check()?;
+
+    let label = describe(cmd);
+    let mut child = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("failed to execute {label}"))?;
+
+    let stdout = child
+        .stdout
+        .take()
+        .map(|pipe| thread::spawn(move || collect(pipe, echo.then(std::io::stdout))));
+    let stderr = child
+        .stderr
+        .take()
+        .map(|pipe| thread::spawn(move || collect(pipe, echo.then(std::io::stderr))));
+
+    let status = match wait_with_deadline(&mut child, timeout)? {
+        Wait::Exited(status) => status,
+        Wait::Interrupted => {
+            return Err(anyhow::Error::new(Interrupted).context(format!("{label} was interrupted")));
+        }
+        Wait::TimedOut => {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(CommandTimeout {
+                command: label,
+                timeout,
+            }
+            .into());
+        }
+    };
+
+    let join = |handle: Option<thread::JoinHandle<Vec<u8>>>| {
+        handle
+            .and_then(|handle| handle.join().ok())
+            .unwrap_or_default()
+    };
+
+    Ok(Output {
+        status,
+        stdout: join(stdout),
+        stderr: join(stderr),
+    })
+}
+
+enum Wait {
+    Exited(ExitStatus),
+    TimedOut,
+    Interrupted,
+}
+
+fn wait_with_deadline(child: &mut Child, timeout: Duration) -> Result<Wait> {
+    let deadline = Instant::now() + timeout;
+    let mut grace_deadline: Option<Instant> = None;
+
+    loop {
+        if let Some(status) = child
+            .try_wait()
+            .context("failed to wait for child process")?
+        {
+            if grace_deadline.is_some() {
+                return Ok(Wait::Interrupted);
+            }
+            return Ok(Wait::Exited(status));
+        }
+
+        match grace_deadline {
+            None if interrupt::requested() => {
+                interrupt::forward(child);
+                grace_deadline = Some(Instant::now() + INTERRUPT_GRACE);
+            }
+            Some(grace) if Instant::now() >= grace => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Ok(Wait::Interrupted);
+            }
+            _ => {}
+        }
+
+        if grace_deadline.is_none() && Instant::now() >= deadline {
+            return Ok(Wait::TimedOut);
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn collect<R: Read, W: Write>(mut pipe: R, mut echo: Option<W>) -> Vec<u8> {
+    let mut captured = Vec::new();
+    let mut buffer = [0u8; 4096];
+
+    while let Ok(read) = pipe.read(&mut buffer) {
+        if read == 0 {
+            break;
+        }
+
+        if let Some(out) = echo.as_mut() {
+            let _ = out.write_all(&buffer[..read]);
+            let _ = out.flush();
+        }
+        captured.extend_from_slice(&buffer[..read]);
+    }
+
+    captured
+}
+
+/// Classifies an error as transient: timeouts, 5xx responses, and dropped connections.
+pub fn is_retryable(err: &anyhow::Error) -> bool {
+    if interrupt::is_interrupted(err) {
+        return false;
+    }
+
+    if err.chain().any(|cause| cause.is::<CommandTimeout>()) {
+        return true;
+    }
+
+    let text = format!("{err:#}").to_lowercase();
+    [
+        "timed out",
+        "timeout",
+        "connection reset",
+        "connection refused",
+        "connection closed",
+        "could not resolve host",
+        "temporary failure in name resolution",
+        "the remote end hung up unexpectedly",
+        "early eof",
+        "http 500",
+        "http 502",
+        "http 503",
+        "http 504",
+        "500 internal server error",
+        "502 bad gateway",
+        "503 service unavailable",
+        "504 gateway timeout",
+    ]
+    .iter()
+    .any(|marker| text.contains(marker))
+}
+
+/// Runs `op` until it succeeds, fails with a non-retryable error, or the policy
+/// runs out of attempts. Every attempt is recorded on the current step.
+pub fn with_retry<T>(
+    ctx: &mut RunContext,
+    policy: &RetryPolicy,
+    label: &str,
+    mut op: impl FnMut(&mut RunContext) -> Result<T>,
+) -> Result<T> {
+    let mut attempt = 1;
+    loop {
+        match op(ctx) {
+            Ok(value) => {
+                if policy.max_attempts > 1 {
+                    ctx.record_attempt(label, attempt, None)?;
+                }
+                return Ok(value);
+            }
+            Err(err) => {
+                if policy.max_attempts > 1 {
+                    ctx.record_attempt(label, attempt, Some(format!("{err:#}")))?;
+                }
+
+                if attempt >= policy.max_attempts || !is_retryable(&err) || interrupt::requested() {
+                    return Err(err);
+                }
+
+                let delay = policy.backoff(attempt);
+                eprintln!(
+                    "    {} failed (attempt {}/{}): {:#}; retrying in {}s",
+                    label,
+                    attempt,
+                    policy.max_attempts,
+                    err,
+                    delay.as_secs()
+                );
+                thread::sleep(delay);
+                attempt += 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::process::Command;
+
+use crate::exec::{self, CommandExt};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReleaseAsset {
+    pub name: String,
+    /// Browser download URL.
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Release {
+    pub tag_name: String,
+    pub assets: Vec<ReleaseAsset>,
+}
+
+/// Read access to releases on a code forge.
+pub trait Forge {
+    /// The release for `tag`, or the latest release when `tag` is `None`.
+    fn release(&self, repo: &str, tag: Option<&str>) -> Result<Release>;
+
+    /// Downloads a (small, text) release asset such as `checksums.txt`.
+    fn asset_text(&self, repo: &str, tag: &str, asset: &str) -> Result<String>;
+}
+
+/// GitHub, through the `gh` CLI so its authentication is reused.
+pub struct GitHub;
+
+impl Forge for GitHub {
+    fn release(&self, repo: &str, tag: Option<&str>) -> Result<Release> {
+        let mut cmd = Command::new("gh");
+        cmd.args(["release", "view"]);
+        if let Some(tag) = tag {
+            cmd.arg(tag);
+        }
+        cmd.args(["--repo", repo, "--json", "tagName,assets"]);
+
+        let output = cmd
+            .output_within(exec::QUERY_TIMEOUT)
+            .context("failed to run gh release view")?;
+        if !output.status.success() {
+            bail!(
+                "gh release view for {} {} failed: {}",
+                repo,
+                tag.unwrap_or("(latest)"),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        serde_json::from_slice(&output.stdout)
+            .with_context(|| format!("failed to parse release of {repo}"))
+    }
+
+    fn asset_text(&self, repo: &str, tag: &str, asset: &str) -> Result<String> {
+        let output = Command::new("gh")
+            .args([
+                "release",
+                "download",
+                tag,
+                "--repo",
+                repo,
+                "--pattern",
+                asset,
+                "--output",
+                "-",
+            ])
+            .output_within(exec::APPLY_TIMEOUT)
+            .context("failed to run gh release download")?;
+        if !output.status.success() {
+            bail!(
+                "downloading {} from {} {} failed: {}",
+                asset,
+                repo,
+                tag,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
@@ -0,0 +1,633 @@
+use crate::source_url::{self, Hosting};
+
+/// Port of Homebrew's `Formulary.class_s`: `foo-bar` -> `FooBar`,
+/// `foo@2` -> `FooAT2`, `gtk+3` -> `Gtkx3`, `lua5.1` -> `Lua51`.
+pub fn class_name(name: &str) -> String {
+    // `name.capitalize`
+    let mut capitalized = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    if let Some(first) = chars.next() {
+        capitalized.extend(first.to_uppercase());
+        capitalized.extend(chars.flat_map(char::to_lowercase));
+    }
+
+    // `gsub(/[-_.\s]([a-zA-Z0-9])/) { $1.upcase }`
+    let mut class_name = String::with_capacity(capitalized.len());
+    let mut chars = capitalized.chars().peekable();
+    while let Some(ch) = chars.next() {
+        let separator = matches!(ch, '-' | '_' | '.') || ch.is_whitespace();
+        match chars.peek() {
+            Some(next) if separator && next.is_ascii_alphanumeric() => {
+                class_name.push(next.to_ascii_uppercase());
+                chars.next();
+            }
+            _ => class_name.push(ch),
+        }
+    }
+
+    // `tr("+", "x")`
+    let class_name = class_name.replace('+', "x");
+
+    // `sub(/(.)@(\d)/, "\\1AT\\2")`
+    let versioned = class_name.char_indices().find(|&(index, ch)| {
+        ch == '@'
+            && index > 0
+            && class_name[index + 1..]
+                .chars()
+                .next()
+                .is_some_and(|next| next.is_ascii_digit())
+    });
+    match versioned {
+        Some((index, _)) => format!("{}AT{}", &class_name[..index], &class_name[index + 1..]),
+        None => class_name,
+    }
+}
+
+/// Whether Ruby would accept `name` as a class constant. Homebrew applies no
+/// fix-up of its own, so names like `2fa` produce a class it cannot load.
+pub fn is_ruby_constant(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|first| first.is_ascii_uppercase())
+        && chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+}
+
+/// Returns the class declared by a formula file (`class Foo < Formula`).
+pub fn parse_class_name(source: &str) -> Option<String> {
+    source.lines().find_map(|line| {
+        let rest = line.trim_start().strip_prefix("class")?;
+        if !rest.starts_with(char::is_whitespace) {
+            return None;
+        }
+
+        let (name, parent) = rest.split_once('<')?;
+        let name = name.trim();
+        let parent = parent.trim_start();
+        let is_formula = parent.strip_prefix("Formula").is_some_and(|tail| {
+            !tail.starts_with(|ch: char| ch.is_ascii_alphanumeric() || ch == '_')
+        });
+
+        (is_formula && is_ruby_constant(name)).then(|| name.to_string())
+    })
+}
+
+/// The `url` a stub formula declares until the real source is filled in.
+pub const PLACEHOLDER_URL: &str = "https://example.com/TODO.tar.gz";
+
+/// Builds a `livecheck` block suited to where the source archive is hosted.
+/// Returns `None` when there is nothing better than Homebrew's defaults.
+pub fn livecheck_block(url: &str) -> Option<String> {
+    let source = source_url::analyze(url);
+    let body = match source.hosting {
+        Hosting::GitHubRelease => "    url :stable\n    strategy :github_latest\n".to_string(),
+        Hosting::GitHubArchive | Hosting::GitLab => {
+            "    url :stable\n    regex(/^v?(\\d+(?:\\.\\d+)+)$/i)\n".to_string()
+        }
+        Hosting::PyPi => "    url :stable\n    strategy :pypi\n".to_string(),
+        Hosting::Npm => "    url :stable\n    strategy :npm\n".to_string(),
+        Hosting::CratesIo => "    url :stable\n    strategy :crate\n".to_string(),
+        Hosting::SourceForge => "    url :stable\n    strategy :sourceforge\n".to_string(),
+        Hosting::Generic => {
+            // Check the directory listing the archive was downloaded from.
+            let name = source.name?;
+            let url = url.split(['?', '#']).next().unwrap_or(url);
+            let (index, _) = url.rsplit_once('/')?;
+            format!(
+                "    url \"{}/\"\n    regex(/href=.*?{}[._-]v?(\\d+(?:\\.\\d+)+)\\.t/i)\n",
+                index,
+                regex_escape(&name)
+            )
+        }
+    };
+
+    Some(format!("  livecheck do\n{body}  end\n"))
+}
+
+/// The livecheck block for a formula built from a git tag.
+pub fn git_tag_livecheck_block() -> String {
+    "  livecheck do\n    url :stable\n    strategy :git\n    regex(/^v?(\\d+(?:\\.\\d+)+)$/i)\n  end\n"
+        .to_string()
+}
+
+/// The livecheck block for a parsed formula: the git tag block for a tagged
+/// `url`, otherwise one for its stable (or first platform) URL. Head-only
+/// formulae and the stub's placeholder URL get none.
+pub fn livecheck_for(formula: &FormulaSource) -> Option<String> {
+    let url = formula.primary_url()?;
+    if formula.tag.is_some() {
+        return Some(git_tag_livecheck_block());
+    }
+    if url == PLACEHOLDER_URL {
+        return None;
+    }
+    livecheck_block(&url)
+}
+
+/// Inserts `block` before `def install` (or the final `end`) of a formula,
+/// unless it already has a `livecheck` block.
+pub fn insert_livecheck(source: &str, block: &str) -> Option<String> {
+    if source
+        .lines()
+        .any(|line| line.trim_start().starts_with("livecheck do"))
+    {
+        return None;
+    }
+
+    let offset = source
+        .find("\n  def install")
+        .map(|index| index + 1)
+        .or_else(|| source.trim_end().rfind("\nend").map(|index| index + 1))?;
+
+    let mut updated = String::with_capacity(source.len() + block.len() + 1);
+    updated.push_str(&source[..offset]);
+    updated.push_str(block);
+    updated.push('\n');
+    updated.push_str(&source[offset..]);
+    Some(updated)
+}
+
+/// The formula with the livecheck block [`livecheck_for`] gives it, or
+/// `None` when it needs none or already has one.
+pub fn add_livecheck(source: &str) -> Option<String> {
+    livecheck_for(&parse(source)).and_then(|block| insert_livecheck(source, &block))
+}
+
+/// Binaries the formula's `def install` puts in `bin`, read from its
+/// `bin.install` lines (`"foo"`, `"target/foo"`, `"foo-cli" => "foo"`). An
+/// install section without any (build systems such as `std_cargo_args` or
+/// `virtualenv_install_with_resources`, or the stub's TODO) is taken to
+/// install a binary named after the formula. `None` when a glob makes the
+/// names unknowable.
+pub fn installed_binaries(source: &str, formula_name: &str) -> Option<Vec<String>> {
+    let mut binaries: Vec<String> = Vec::new();
+    let mut in_install = false;
+
+    for line in source.lines() {
+        if line == "  def install" {
+            in_install = true;
+            continue;
+        }
+        if !in_install {
+            continue;
+        }
+        if line == "  end" {
+            break;
+        }
+
+        let line = line.trim();
+        let Some(args) = line
+            .strip_prefix("bin.install_symlink ")
+            .or_else(|| line.strip_prefix("bin.install "))
+        else {
+            continue;
+        };
+        if args.contains('*') || args.contains("Dir[") {
+            return None;
+        }
+        // With `"from" => "to"`, only the name after the arrow ends up in bin.
+        let names = match args.split_once("=>") {
+            Some((_, renamed)) => renamed,
+            None => args,
+        };
+        binaries.extend(
+            names
+                .split('"')
+                .skip(1)
+                .step_by(2)
+                .map(|name| name.rsplit('/').next().unwrap_or(name).to_string()),
+        );
+    }
+
+    if binaries.is_empty() {
+        binaries.push(formula_name.to_string());
+    }
+    Some(binaries)
+}
+
+fn regex_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if "\\.+*?()|[]{}^$/".contains(ch) {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+/// The parts of a formula file the upstream checker and verification need.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FormulaSource {
+    pub class_name: Option<String>,
+    pub homepage: Option<String>,
+    pub url: Option<String>,
+    pub version: Option<String>,
+    pub livecheck: Option<LivecheckSpec>,
+    /// `tag:` and `revision:` of a git `url`.
+    pub tag: Option<String>,
+    pub revision: Option<String>,
+    /// The `head` URL, when given as a one-line stanza.
+    pub head: Option<String>,
+    /// `url`/`sha256` pairs declared inside `on_macos`/`on_linux` and
+    /// `on_arm`/`on_intel` blocks.
+    pub platforms: Vec<PlatformSource>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LivecheckSpec {
+    /// Literal URL, or `:stable`/`:homepage`/`:head` as written.
+    pub url: Option<String>,
+    pub strategy: Option<String>,
+    /// Ruby regex literal, e.g. `/^v?(\d+(?:\.\d+)+)$/i`.
+    pub regex: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlatformSource {
+    /// `on_macos` or `on_linux`, when nested in one.
+    pub os: Option<String>,
+    /// `on_arm` or `on_intel`, when nested in one.
+    pub arch: Option<String>,
+    pub url: Option<String>,
+    pub sha256: Option<String>,
+}
+
+impl PlatformSource {
+    pub fn label(&self) -> String {
+        [self.os.as_deref(), self.arch.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    pub fn has_checksum(&self) -> bool {
+        self.sha256.as_deref().is_some_and(is_sha256)
+    }
+}
+
+impl FormulaSource {
+    /// The stable URL, or the first per-platform one.
+    pub fn primary_url(&self) -> Option<String> {
+        self.url.clone().or_else(|| {
+            self.platforms
+                .iter()
+                .find_map(|platform| platform.url.clone())
+        })
+    }
+
+    /// Explicit `version`, or the one in the stable URL or its git tag.
+    pub fn current_version(&self) -> Option<String> {
+        self.version.clone().or_else(|| {
+            let source = source_url::analyze(&self.primary_url()?);
+            source.version.or_else(|| {
+                let name = source.name.unwrap_or_default();
+                source_url::version_from_tag(self.tag.as_deref()?, &name)
+            })
+        })
+    }
+
+    pub fn has_revision(&self) -> bool {
+        self.revision.as_deref().is_some_and(|revision| {
+            revision.len() == 40 && revision.chars().all(|ch| ch.is_ascii_hexdigit())
+        })
+    }
+
+    /// The URL livecheck should look at, resolving `:stable` and `:homepage`.
+    pub fn livecheck_url(&self) -> Option<String> {
+        match self.livecheck.as_ref().and_then(|spec| spec.url.as_deref()) {
+            Some(":homepage") => self.homepage.clone(),
+            Some(":stable") | None => self.primary_url(),
+            Some(url) if url.starts_with(':') => None,
+            Some(url) => Some(url.to_string()),
+        }
+    }
+}
+
+/// What a line opened, so `end` can tell which context it closes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Block {
+    Class,
+    Livecheck,
+    Platform(&'static str),
+    Other,
+}
+
+const PLATFORM_BLOCKS: &[&str] = &["on_macos", "on_linux", "on_arm", "on_intel"];
+
+/// Reads top-level `url`, `version`, `homepage`, the `livecheck` block and
+/// per-platform `url`/`sha256` from formula source. Other nested blocks
+/// (`resource`, `head`, `def install`, ...) are skipped so their URLs are not
+/// mistaken for the stable one.
+pub fn parse(source: &str) -> FormulaSource {
+    let mut formula = FormulaSource {
+        class_name: parse_class_name(source),
+        ..FormulaSource::default()
+    };
+    let mut stack: Vec<Block> = Vec::new();
+    let mut url_continues = false;
+
+    for line in source.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if line == "end" {
+            stack.pop();
+            continue;
+        }
+
+        let opens_block = line.ends_with(" do") || line.contains(" do |");
+        let opens_statement = [
+            "class ", "def ", "if ", "unless ", "case ", "while ", "until ",
+        ]
+        .iter()
+        .any(|keyword| line.starts_with(keyword))
+            || line == "begin";
+        if opens_statement || opens_block {
+            let block = if stack.is_empty() && line.starts_with("class ") {
+                Block::Class
+            } else if stack == [Block::Class] && line == "livecheck do" {
+                formula.livecheck = Some(LivecheckSpec::default());
+                Block::Livecheck
+            } else if let Some(name) = PLATFORM_BLOCKS
+                .iter()
+                .find(|name| line == format!("{name} do"))
+                .filter(|_| in_platform_context(&stack))
+            {
+                Block::Platform(name)
+            } else {
+                Block::Other
+            };
+            stack.push(block);
+            continue;
+        }
+
+        match stack.as_slice() {
+            [Block::Class] => {
+                // `tag:`/`revision:` follow `url` on the same or continuation lines.
+                if url_continues {
+                    formula.tag = formula.tag.take().or_else(|| option_value(line, "tag"));
+                    formula.revision = formula
+                        .revision
+                        .take()
+                        .or_else(|| option_value(line, "revision"));
+                    url_continues = line.ends_with(',');
+                    continue;
+                }
+
+                if let Some(value) = directive(line, "url") {
+                    if formula.url.is_none() {
+                        formula.url = Some(value);
+                        formula.tag = option_value(line, "tag");
+                        formula.revision = option_value(line, "revision");
+                        url_continues = line.ends_with(',');
+                    }
+                } else if let Some(value) = directive(line, "head") {
+                    formula.head.get_or_insert(value);
+                } else if let Some(value) = directive(line, "version") {
+                    formula.version.get_or_insert(value);
+                } else if let Some(value) = directive(line, "homepage") {
+                    formula.homepage.get_or_insert(value);
+                }
+            }
+            [Block::Class, Block::Livecheck] => {
+                if let Some(spec) = formula.livecheck.as_mut() {
+                    parse_livecheck_line(spec, line);
+                }
+            }
+            [Block::Class, platforms @ ..] if in_platform_context(&stack) => {
+                let url = directive(line, "url");
+                let sha256 = directive(line, "sha256");
+                if url.is_none() && sha256.is_none() {
+                    continue;
+                }
+
+                let (os, arch) = platform_names(platforms);
+                let index = match formula
+                    .platforms
+                    .iter()
+                    .position(|platform| platform.os == os && platform.arch == arch)
+                {
+                    Some(index) => index,
+                    None => {
+                        formula.platforms.push(PlatformSource {
+                            os,
+                            arch,
+                            ..PlatformSource::default()
+                        });
+                        formula.platforms.len() - 1
+                    }
+                };
+                let platform = &mut formula.platforms[index];
+                if url.is_some() {
+                    platform.url = url;
+                }
+                if sha256.is_some() {
+                    platform.sha256 = sha256;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    formula
+}
+
+fn in_platform_context(stack: &[Block]) -> bool {
+    matches!(stack.first(), Some(Block::Class))
+        && stack[1..]
+            .iter()
+            .all(|block| matches!(block, Block::Platform(_)))
+}
+
+fn platform_names(blocks: &[Block]) -> (Option<String>, Option<String>) {
+    let find = |names: &[&str]| {
+        blocks.iter().find_map(|block| match block {
+            Block::Platform(name) if names.contains(name) => Some(name.to_string()),
+            _ => None,
+        })
+    };
+    (
+        find(&["on_macos", "on_linux"]),
+        find(&["on_arm", "on_intel"]),
+    )
+}
+
+fn parse_livecheck_line(spec: &mut LivecheckSpec, line: &str) {
+    if let Some(value) = directive(line, "url") {
+        spec.url = Some(value);
+    } else if let Some(value) = directive(line, "strategy") {
+        spec.strategy = Some(value.trim_start_matches(':').to_string());
+    } else if let Some(rest) = line.strip_prefix("regex") {
+        let rest = rest.trim();
+        let rest = rest
+            .strip_prefix('(')
+            .and_then(|inner| inner.strip_suffix(')'))
+            .unwrap_or(rest);
+        spec.regex = Some(rest.trim().to_string());
+    }
+}
+
+fn is_sha256(value: &str) -> bool {
+    value.len() == 64 && value.chars().all(|ch| ch.is_ascii_hexdigit())
+}
+
+/// Reads the quoted value of a `key: "value"` option anywhere in `line`.
+fn option_value(line: &str, key: &str) -> Option<String> {
+    let pattern = format!("{key}:");
+    let mut rest = line;
+    while let Some(index) = rest.find(&pattern) {
+        let boundary = rest[..index]
+            .chars()
+            .next_back()
+            .is_none_or(|ch| ch == ',' || ch.is_whitespace());
+        let after = rest[index + pattern.len()..].trim_start();
+        if boundary {
+            if let Some(quoted) = after.strip_prefix('"') {
+                return quoted.split('"').next().map(str::to_string);
+            }
+        }
+        rest = &rest[index + pattern.len()..];
+    }
+    None
+}
+
+/// Reads `<keyword> "value"` or `<keyword> :symbol`, ignoring trailing options
+/// such as `url "...", using: :git`.
+fn directive(line: &str, keyword: &str) -> Option<String> {
+    let rest = line.strip_prefix(keyword)?;
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    let rest = rest.trim_start();
+    if let Some(quoted) = rest.strip_prefix('"') {
+        return quoted.split('"').next().map(str::to_string);
+    }
+    if rest.starts_with(':') {
+        let end = rest
+            .find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '_' || ch == ':'))
+            .unwrap_or(rest.len());
+        return Some(rest[..end].to_string());
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn class_names_match_homebrew_core() {
+        // Formula names from homebrew-core and the classes their files declare.
+        let corpus = [
+            ("wget", "Wget"),
+            ("git-lfs", "GitLfs"),
+            ("ca-certificates", "CaCertificates"),
+            ("gst-plugins-base", "GstPluginsBase"),
+            ("s-lang", "SLang"),
+            ("docbook-xsl", "DocbookXsl"),
+            ("openssl@3", "OpensslAT3"),
+            ("node@20", "NodeAT20"),
+            ("llvm@18", "LlvmAT18"),
+            ("icu4c@76", "Icu4cAT76"),
+            ("lua@5.1", "LuaAT51"),
+            ("python@3.12", "PythonAT312"),
+            ("php@8.2", "PhpAT82"),
+            ("ruby@3.3", "RubyAT33"),
+            ("gtk+3", "Gtkx3"),
+            ("libxml++", "Libxmlxx"),
+            ("libxml++3", "Libxmlxx3"),
+            ("libsigc++@2", "LibsigcxxAT2"),
+            ("x264", "X264"),
+            ("py3cairo", "Py3cairo"),
+            ("s3cmd", "S3cmd"),
+            ("lua5.1", "Lua51"),
+            ("bdw-gc", "BdwGc"),
+        ];
+        for (name, expected) in corpus {
+            let class = class_name(name);
+            assert_eq!(class, expected, "class name of {name}");
+            assert!(is_ruby_constant(&class), "{class} is not a constant");
+        }
+    }
+
+    #[test]
+    fn class_names_of_unusual_names() {
+        let cases = [
+            // `capitalize` lowercases the rest of the name.
+            ("FooBar", "Foobar"),
+            ("foo_bar", "FooBar"),
+            ("foo bar", "FooBar"),
+            // A leading separator is dropped along with the next letter's case.
+            (".foo", "Foo"),
+            ("-foo", "Foo"),
+            // Only a separator followed by a letter or digit is removed.
+            ("foo--bar", "Foo-Bar"),
+            ("foo-", "Foo-"),
+            // `@` needs a character before it and a digit after it, and only
+            // the first one is replaced.
+            ("@2", "@2"),
+            ("foo@bar", "Foo@bar"),
+            ("foo@2@3", "FooAT2@3"),
+            ("2fa", "2fa"),
+            ("", ""),
+        ];
+        for (name, expected) in cases {
+            assert_eq!(class_name(name), expected, "class name of {name:?}");
+        }
+    }
+
+    #[test]
+    fn ruby_constants() {
+        for valid in ["Foo", "FooAT2", "Foo_Bar", "X264", "A"] {
+            assert!(is_ruby_constant(valid), "{valid} was rejected");
+        }
+        for invalid in [
+            "", "foo", "2fa", "Foo-Bar", "Foo@bar", "Gtk+3", "Föo", "_Foo",
+        ] {
+            assert!(!is_ruby_constant(invalid), "{invalid} was accepted");
+        }
+    }
+
+    #[test]
+    fn adds_a_livecheck_block_only_when_missing() {
+        let formula = "class Foo < Formula\n  url \"https://github.com/acme/foo/archive/refs/tags/v1.0.tar.gz\"\n\n  def install\n  end\nend\n";
+        let updated = add_livecheck(formula).unwrap();
+        assert!(updated.contains("  livecheck do\n    url :stable\n"));
+        assert!(updated.find("livecheck do") < updated.find("def install"));
+        assert_eq!(add_livecheck(&updated), None);
+
+        let stub = format!("class Foo < Formula\n  url \"{PLACEHOLDER_URL}\"\nend\n");
+        assert_eq!(add_livecheck(&stub), None);
+    }
+
+    #[test]
+    fn parses_formula_class_declarations() {
+        let cases = [
+            ("class Foo < Formula\nend\n", Some("Foo")),
+            ("  class FooAT2 < Formula\n", Some("FooAT2")),
+            ("class Foo<Formula\n", Some("Foo")),
+            ("class Foo < Formula # a comment\n", Some("Foo")),
+            (
+                "class Helper\nend\n\nclass Foo < Formula\nend\n",
+                Some("Foo"),
+            ),
+            ("class Foo < FormulaBase\n", None),
+            ("class Foo < AbstractFormula\n", None),
+            ("class foo < Formula\n", None),
+            ("class Foo-Bar < Formula\n", None),
+            ("classy Foo < Formula\n", None),
+            ("# class Foo < Formula\n", None),
+            ("", None),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(
+                parse_class_name(source).as_deref(),
+                expected,
+                "class in {source:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::inputs::TestStrategy;
+
+/// The `test do` block a formula was generated with, recorded in the run
+/// state for `runs show`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FormulaTest {
+    pub strategy: TestStrategy,
+    /// The binary a `cli` test runs, or the library a `library` test links.
+    pub target: String,
+    /// For `command`: the command line, starting with an installed binary.
+    pub command: Option<String>,
+    /// For `command`: text the command's output must contain.
+    pub expect: Option<String>,
+    /// Head-only formulae have `HEAD-<sha>` versions, so `cli` tests only
+    /// check that `--version` succeeds.
+    pub head_only: bool,
+}
+
+impl FormulaTest {
+    /// The `test do` block, indented for the class body.
+    pub fn render(&self) -> String {
+        let mut out = String::from("  test do\n");
+        match self.strategy {
+            TestStrategy::Cli if self.head_only => {
+                out.push_str(&format!(
+                    "    system bin/\"{}\", \"--version\"\n",
+                    self.target
+                ));
+            }
+            TestStrategy::Cli => out.push_str(&format!(
+                "    assert_match version.to_s, shell_output(\"#{{bin}}/{} --version\")\n",
+                self.target
+            )),
+            TestStrategy::Library => {
+                out.push_str("    (testpath/\"test.c\").write <<~C\n");
+                out.push_str(&format!("      #include <{}.h>\n\n", self.target));
+                out.push_str("      int main(void) {\n        return 0;\n      }\n");
+                out.push_str("    C\n");
+                out.push_str(&format!(
+                    "    system ENV.cc, \"test.c\", \"-I#{{include}}\", \"-L#{{lib}}\", \"-l{}\", \"-o\", \"test\"\n",
+                    self.target
+                ));
+                out.push_str("    system \"./test\"\n");
+            }
+            TestStrategy::Command => {
+                let command = format!(
+                    "\"#{{bin}}/{}\"",
+                    ruby_escape(self.command.as_deref().unwrap_or(&self.target))
+                );
+                match &self.expect {
+                    Some(expect) => out.push_str(&format!(
+                        "    assert_match \"{}\", shell_output({})\n",
+                        ruby_escape(expect),
+                        command
+                    )),
+                    None => out.push_str(&format!("    system {command}\n")),
+                }
+            }
+        }
+        out.push_str("  end\n");
+        out
+    }
+}
+
+impl fmt::Display for FormulaTest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.strategy, &self.command) {
+            (TestStrategy::Command, Some(command)) => write!(f, "command '{command}'")?,
+            (TestStrategy::Library, _) => write!(f, "library (links -l{})", self.target)?,
+            _ => write!(f, "version ({} --version)", self.target)?,
+        }
+        if let Some(expect) = &self.expect {
+            write!(f, ", expecting '{expect}'")?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks that `--test-command` starts with the name of an installed binary.
+pub fn normalize_command(command: String) -> Result<String> {
+    let command = command.trim();
+    let Some(binary) = command.split_whitespace().next() else {
+        bail!("--test-command is empty");
+    };
+    if binary.contains('/') {
+        bail!(
+            "--test-command must start with the name of an installed binary, not a path ('{}')",
+            binary
+        );
+    }
+    Ok(command.to_string())
+}
+
+/// The library a `library` test links: the formula name without a `lib`
+/// prefix or `@<version>` suffix (`libfoo@2` -> `foo`).
+pub fn library_name(formula_name: &str) -> String {
+    let name = formula_name.split('@').next().unwrap_or(formula_name);
+    name.strip_prefix("lib")
+        .filter(|rest| !rest.is_empty())
+        .unwrap_or(name)
+        .to_string()
+}
+
+/// Escapes text for a double-quoted Ruby string.
+fn ruby_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace("#{", "\\#{")
+}
+
+/// Whether the body of `test do` is only a placeholder: comments, blank
+/// lines, and the `system "false"` that `brew create` writes.
+pub fn has_placeholder_test(source: &str) -> bool {
+    match test_body(source) {
+        Some(body) => body.iter().all(|line| {
+            let line = line.trim();
+            line.is_empty() || line.starts_with('#') || line == "system \"false\""
+        }),
+        None => true,
+    }
+}
+
+fn test_body(source: &str) -> Option<Vec<&str>> {
+    let mut lines = source.lines().skip_while(|line| *line != "  test do");
+    lines.next()?;
+    Some(lines.take_while(|line| *line != "  end").collect())
+}
+
+/// Replaces the `test do` block with `test`, or adds one before the class's
+/// closing `end`. Unless `force` is set, only a placeholder test is replaced.
+pub fn set_test(source: &str, test: &FormulaTest, force: bool) -> String {
+    if !force && !has_placeholder_test(source) {
+        return source.to_string();
+    }
+
+    let block = test.render();
+    let mut out = String::new();
+    let mut lines = source.lines();
+    let mut replaced = false;
+    while let Some(line) = lines.next() {
+        if line == "  test do" && !replaced {
+            for skipped in lines.by_ref() {
+                if skipped == "  end" {
+                    break;
+                }
+            }
+            out.push_str(&block);
+            replaced = true;
+            continue;
+        }
+        if line == "end" && !replaced {
+            out.push('\n');
+            out.push_str(&block);
+            replaced = true;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
@@ -1,8 +1,19 @@
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, ValueEnum)]
+use crate::completions;
+use crate::dependency::{self, Dependency};
+use crate::formula_test;
+use crate::license::License;
+use crate::python;
+use crate::service::{self, Service};
+use crate::validate;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ValueEnum, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case")]
 pub enum Visibility {
     Public,
@@ -14,6 +25,216 @@ pub enum Visibility {
 pub enum FormulaMode {
     Stub,
     BrewCreate,
+    GithubRelease,
+    Python,
+}
+
+/// Where a formula's source comes from.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ValueEnum, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SourceKind {
+    /// A release archive (`url` + `sha256`).
+    Tarball,
+    /// A tag of a git repository, pinned to its commit (`url ..., tag:, revision:`).
+    GitTag,
+    /// Only the tip of a git branch (`head ..., branch:`).
+    Head,
+}
+
+/// Which CLI framework generates the tool's shell completions.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ValueEnum, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Completions {
+    /// clap_complete's `COMPLETE=<shell>` environment variable.
+    Clap,
+    /// cobra's `completion <shell>` subcommand.
+    Cobra,
+    None,
+}
+
+/// What the generated `test do` block checks.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ValueEnum, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TestStrategy {
+    /// `<binary> --version` prints the formula's version.
+    Cli,
+    /// A C program compiles and links against the library.
+    Library,
+    /// A `--test-command` succeeds, printing `--test-expect` when given.
+    Command,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ValueEnum, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Platform {
+    #[serde(rename = "macos-arm64")]
+    #[value(name = "macos-arm64")]
+    MacosArm64,
+    #[serde(rename = "macos-x86_64")]
+    #[value(name = "macos-x86_64")]
+    MacosX86_64,
+    #[serde(rename = "linux-arm64")]
+    #[value(name = "linux-arm64")]
+    LinuxArm64,
+    #[serde(rename = "linux-x86_64")]
+    #[value(name = "linux-x86_64")]
+    LinuxX86_64,
+}
+
+/// A prebuilt artifact for one platform, given as `--artifact <platform>=<url>`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PlatformArtifact {
+    pub platform: Platform,
+    pub url: String,
+}
+
+impl FromStr for PlatformArtifact {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (platform, url) = value
+            .split_once('=')
+            .ok_or_else(|| format!("expected <platform>=<url>, got '{value}'"))?;
+        let platform = Platform::from_str(platform.trim(), true).map_err(|_| {
+            let names: Vec<String> = Platform::value_variants()
+                .iter()
+                .map(|platform| value_name(*platform))
+                .collect();
+            format!(
+                "unknown platform '{}'; expected one of {}",
+                platform.trim(),
+                names.join(", ")
+            )
+        })?;
+
+        Ok(Self {
+            platform,
+            url: url.trim().to_string(),
+        })
+    }
+}
+
+impl fmt::Display for PlatformArtifact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", value_name(self.platform), self.url)
+    }
+}
+
+/// Identifies an `Inputs` field so steps can declare what they read and
+/// resumed runs can tell which inputs drifted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputField {
+    Owner,
+    Tap,
+    RepoName,
+    Visibility,
+    Branch,
+    FormulaMode,
+    FormulaUrl,
+    FormulaName,
+    Artifacts,
+    PythonLock,
+    Python,
+    SourceKind,
+    SourceTag,
+    HeadBranch,
+    DependsOn,
+    Service,
+    Caveats,
+    Completions,
+    Manpage,
+    TestStrategy,
+    TestCommand,
+    TestExpect,
+    License,
+}
+
+impl InputField {
+    pub const ALL: &'static [InputField] = &[
+        InputField::Owner,
+        InputField::Tap,
+        InputField::RepoName,
+        InputField::Visibility,
+        InputField::Branch,
+        InputField::FormulaMode,
+        InputField::FormulaUrl,
+        InputField::FormulaName,
+        InputField::Artifacts,
+        InputField::PythonLock,
+        InputField::Python,
+        InputField::SourceKind,
+        InputField::SourceTag,
+        InputField::HeadBranch,
+        InputField::DependsOn,
+        InputField::Service,
+        InputField::Caveats,
+        InputField::Completions,
+        InputField::Manpage,
+        InputField::TestStrategy,
+        InputField::TestCommand,
+        InputField::TestExpect,
+        InputField::License,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            InputField::Owner => "owner",
+            InputField::Tap => "tap",
+            InputField::RepoName => "repo-name",
+            InputField::Visibility => "visibility",
+            InputField::Branch => "branch",
+            InputField::FormulaMode => "formula-mode",
+            InputField::FormulaUrl => "formula-url",
+            InputField::FormulaName => "formula-name",
+            InputField::Artifacts => "artifact",
+            InputField::PythonLock => "python-lock",
+            InputField::Python => "python",
+            InputField::SourceKind => "source-kind",
+            InputField::SourceTag => "source-tag",
+            InputField::HeadBranch => "head-branch",
+            InputField::DependsOn => "depends-on",
+            InputField::Service => "service",
+            InputField::Caveats => "caveats",
+            InputField::Completions => "completions",
+            InputField::Manpage => "manpage",
+            InputField::TestStrategy => "test-strategy",
+            InputField::TestCommand => "test-command",
+            InputField::TestExpect => "test-expect",
+            InputField::License => "license",
+        }
+    }
+}
+
+/// Values passed on the command line (or gathered by the wizard). For a new
+/// run, `None` takes the default; when resuming, it keeps the stored input.
+#[derive(Debug, Clone, Default)]
+pub struct InputOverrides {
+    pub owner: Option<String>,
+    pub tap: Option<String>,
+    pub repo_name: Option<String>,
+    pub visibility: Option<Visibility>,
+    pub branch: Option<String>,
+    pub formula_mode: Option<FormulaMode>,
+    pub formula_url: Option<String>,
+    pub formula_name: Option<String>,
+    /// Empty keeps the stored artifacts when resuming.
+    pub artifacts: Vec<PlatformArtifact>,
+    pub python_lock: Option<String>,
+    pub python: Option<String>,
+    pub source_kind: Option<SourceKind>,
+    pub source_tag: Option<String>,
+    pub head_branch: Option<String>,
+    /// Empty keeps the stored dependencies when resuming.
+    pub depends_on: Vec<Dependency>,
+    pub service_run: Option<String>,
+    pub service_keep_alive: Option<bool>,
+    pub service_log_path: Option<String>,
+    pub caveats: Option<String>,
+    pub completions: Option<Completions>,
+    pub manpage: Option<String>,
+    pub test_strategy: Option<TestStrategy>,
+    pub test_command: Option<String>,
+    pub test_expect: Option<String>,
+    pub license: Option<License>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,31 +247,174 @@ pub struct Inputs {
     pub formula_mode: FormulaMode,
     pub formula_url: Option<String>,
     pub formula_name: Option<String>,
+    pub artifacts: Vec<PlatformArtifact>,
+    /// Absolute path of the requirements lock for `python` mode.
+    pub python_lock: Option<String>,
+    /// `python@3.x` formula for `python` mode; unset uses the lock's Python or
+    /// the default.
+    pub python: Option<String>,
+    pub source_kind: SourceKind,
+    /// Tag to pin for `git-tag` sources.
+    pub source_tag: Option<String>,
+    /// Branch for the `head` stanza of `git-tag` and `head` sources.
+    pub head_branch: Option<String>,
+    /// `depends_on` lines for the generated formula, in canonical order.
+    pub depends_on: Vec<Dependency>,
+    /// `service do` block for formulae that run as background agents.
+    pub service: Option<Service>,
+    /// Text of the `def caveats` heredoc.
+    pub caveats: Option<String>,
+    pub completions: Completions,
+    /// Man page to install, relative to the source tree.
+    pub manpage: Option<String>,
+    pub test_strategy: TestStrategy,
+    /// Command line for the `command` test strategy.
+    pub test_command: Option<String>,
+    /// Output the `command` test expects.
+    pub test_expect: Option<String>,
+    /// SPDX license to use instead of detecting one from the source archive.
+    pub license: Option<License>,
 }
 
 impl Inputs {
-    pub fn new(
-        owner: String,
-        tap: String,
-        repo_name: Option<String>,
-        visibility: Visibility,
-        branch: String,
-        formula_mode: FormulaMode,
-        formula_url: Option<String>,
-        formula_name: Option<String>,
-    ) -> Result<Self> {
-        let owner = normalize_token("owner", owner)?;
-        let tap = normalize_token("tap", tap)?;
-        let branch = normalize_branch(branch)?;
-        let formula_url = formula_url
+    /// Validates `values` and fills in defaults for a new run.
+    pub fn new(values: InputOverrides) -> Result<Self> {
+        let owner = values
+            .owner
+            .ok_or_else(|| anyhow::anyhow!("--owner is required"))?;
+        let tap = values
+            .tap
+            .ok_or_else(|| anyhow::anyhow!("--tap is required"))?;
+        let repo_name = values.repo_name;
+        let visibility = values.visibility.unwrap_or(Visibility::Public);
+        let formula_mode = values.formula_mode.unwrap_or(FormulaMode::Stub);
+
+        let owner = normalize_owner(owner)?;
+        let tap = normalize_tap(tap)?;
+        let branch = normalize_branch(values.branch.unwrap_or_else(|| "main".to_string()))?;
+        let formula_url = values
+            .formula_url
             .map(|value| value.trim().to_string())
             .filter(|value| !value.is_empty());
-        let formula_name = formula_name
-            .map(|value| normalize_token("formula name", value))
+        let formula_name = values
+            .formula_name
+            .map(normalize_formula_name)
+            .transpose()?;
+        let artifacts = normalize_artifacts(values.artifacts, formula_mode)?;
+        let python_lock = values
+            .python_lock
+            .map(|path| normalize_python_lock(path, formula_mode))
             .transpose()?;
+        let python = values
+            .python
+            .map(|python| {
+                if formula_mode != FormulaMode::Python {
+                    bail!("--python is only supported with formula-mode python");
+                }
+                python::normalize_python(&python)
+            })
+            .transpose()?;
+
+        let depends_on = dependency::normalize(values.depends_on)?;
+        let service = match values.service_run {
+            Some(run) => Some(Service::new(
+                &run,
+                values.service_keep_alive.unwrap_or(false),
+                values.service_log_path,
+            )?),
+            None if values.service_keep_alive.is_some() || values.service_log_path.is_some() => {
+                bail!("--service-keep-alive and --service-log-path need --service-run");
+            }
+            None => None,
+        };
+        let caveats = values
+            .caveats
+            .map(service::normalize_caveats)
+            .transpose()?
+            .flatten();
+        let completions = values.completions.unwrap_or(Completions::None);
+        let manpage = values
+            .manpage
+            .map(completions::normalize_manpage)
+            .transpose()?;
+
+        let test_strategy = values.test_strategy.unwrap_or(TestStrategy::Cli);
+        let test_command = values
+            .test_command
+            .map(formula_test::normalize_command)
+            .transpose()?;
+        let test_expect = values
+            .test_expect
+            .map(|expect| expect.trim().to_string())
+            .filter(|expect| !expect.is_empty());
+        match test_strategy {
+            TestStrategy::Command if test_command.is_none() => {
+                bail!("--test-command is required when test-strategy is command");
+            }
+            TestStrategy::Command => {}
+            _ if test_command.is_some() || test_expect.is_some() => {
+                bail!("--test-command and --test-expect need --test-strategy command");
+            }
+            _ => {}
+        }
+        let source_kind = values.source_kind.unwrap_or(SourceKind::Tarball);
+        let source_tag = values
+            .source_tag
+            .map(|tag| normalize_git_ref(tag, validate::tag))
+            .transpose()?;
+        let mut head_branch = values
+            .head_branch
+            .map(|branch| normalize_git_ref(branch, validate::branch))
+            .transpose()?;
+
+        match source_kind {
+            SourceKind::Tarball => {
+                if source_tag.is_some() || head_branch.is_some() {
+                    bail!("--source-tag and --head-branch need --source-kind git-tag or head");
+                }
+            }
+            SourceKind::GitTag | SourceKind::Head => {
+                if formula_mode != FormulaMode::Stub {
+                    bail!(
+                        "source-kind {} is only supported with formula-mode stub",
+                        value_name(source_kind)
+                    );
+                }
+                if !artifacts.is_empty() {
+                    bail!("--artifact needs source-kind tarball");
+                }
+                if formula_url.is_none() {
+                    bail!(
+                        "formula-url (the git repository) is required when source-kind is {}",
+                        value_name(source_kind)
+                    );
+                }
+            }
+        }
+        match source_kind {
+            SourceKind::GitTag if source_tag.is_none() => {
+                bail!("--source-tag is required when source-kind is git-tag");
+            }
+            SourceKind::Head if source_tag.is_some() => {
+                bail!("--source-tag cannot be used with source-kind head");
+            }
+            SourceKind::Head => {
+                head_branch.get_or_insert_with(|| "main".to_string());
+            }
+            _ => {}
+        }
+
+        if formula_mode != FormulaMode::Stub && formula_url.is_none() {
+            bail!(
+                "formula-url is required when formula-mode is {}",
+                value_name(formula_mode)
+            );
+        }
 
-        if matches!(formula_mode, FormulaMode::BrewCreate) && formula_url.is_none() {
-            bail!("formula-url is required when formula-mode is brew-create");
+        if formula_mode == FormulaMode::Stub {
+            // The stub is written to Formula/<tap>.rb.
+            validate::formula_name(&tap)
+                .context("the stub formula is named after the tap; use a lowercase tap name")?;
         }
 
         if tap.starts_with("homebrew-") {
@@ -61,7 +425,7 @@ impl Inputs {
         }
 
         let repo_name = match repo_name {
-            Some(name) => normalize_token("repo name", name)?,
+            Some(name) => normalize_repo_name(name)?,
             None => format!("homebrew-{}", tap),
         };
 
@@ -81,38 +445,250 @@ impl Inputs {
             formula_mode,
             formula_url,
             formula_name,
+            artifacts,
+            python_lock,
+            python,
+            source_kind,
+            source_tag,
+            head_branch,
+            depends_on,
+            service,
+            caveats,
+            completions,
+            manpage,
+            test_strategy,
+            test_command,
+            test_expect,
+            license: values.license,
         })
     }
 
     pub fn repo_slug(&self) -> String {
         format!("{}/{}", self.owner, self.repo_name)
     }
+
+    /// Applies resume-time overrides and re-validates the result.
+    pub fn with_overrides(&self, overrides: InputOverrides) -> Result<Self> {
+        let default_repo = self.repo_name == format!("homebrew-{}", self.tap);
+        let repo_name = match overrides.repo_name {
+            Some(name) => Some(name),
+            // Keep deriving the repo name from the tap unless it was set explicitly.
+            None if default_repo => None,
+            None => Some(self.repo_name.clone()),
+        };
+
+        // A different source kind starts from its own defaults.
+        let source_kind = overrides.source_kind.unwrap_or(self.source_kind);
+        let same_kind = source_kind == self.source_kind;
+
+        let artifacts = if overrides.artifacts.is_empty() {
+            self.artifacts.clone()
+        } else {
+            overrides.artifacts
+        };
+        let depends_on = if overrides.depends_on.is_empty() {
+            self.depends_on.clone()
+        } else {
+            overrides.depends_on
+        };
+        let stored_service = self.service.as_ref();
+        let test_strategy = overrides.test_strategy.unwrap_or(self.test_strategy);
+        let same_strategy = test_strategy == self.test_strategy;
+
+        Self::new(InputOverrides {
+            owner: Some(overrides.owner.unwrap_or_else(|| self.owner.clone())),
+            tap: Some(overrides.tap.unwrap_or_else(|| self.tap.clone())),
+            repo_name,
+            visibility: Some(overrides.visibility.unwrap_or(self.visibility)),
+            branch: Some(overrides.branch.unwrap_or_else(|| self.branch.clone())),
+            formula_mode: Some(overrides.formula_mode.unwrap_or(self.formula_mode)),
+            formula_url: overrides.formula_url.or_else(|| self.formula_url.clone()),
+            formula_name: overrides.formula_name.or_else(|| self.formula_name.clone()),
+            artifacts,
+            python_lock: overrides.python_lock.or_else(|| self.python_lock.clone()),
+            python: overrides.python.or_else(|| self.python.clone()),
+            source_kind: Some(source_kind),
+            source_tag: overrides
+                .source_tag
+                .or_else(|| self.source_tag.clone().filter(|_| same_kind)),
+            head_branch: overrides
+                .head_branch
+                .or_else(|| self.head_branch.clone().filter(|_| same_kind)),
+            depends_on,
+            service_run: overrides
+                .service_run
+                .or_else(|| stored_service.map(|service| service.run.join(" "))),
+            service_keep_alive: overrides
+                .service_keep_alive
+                .or_else(|| stored_service.map(|service| service.keep_alive)),
+            service_log_path: overrides
+                .service_log_path
+                .or_else(|| stored_service.and_then(|service| service.log_path.clone())),
+            caveats: overrides.caveats.or_else(|| self.caveats.clone()),
+            completions: Some(overrides.completions.unwrap_or(self.completions)),
+            manpage: overrides.manpage.or_else(|| self.manpage.clone()),
+            test_strategy: Some(test_strategy),
+            test_command: overrides
+                .test_command
+                .or_else(|| self.test_command.clone().filter(|_| same_strategy)),
+            test_expect: overrides
+                .test_expect
+                .or_else(|| self.test_expect.clone().filter(|_| same_strategy)),
+            license: overrides.license.or_else(|| self.license.clone()),
+        })
+    }
+
+    pub fn field_value(&self, field: InputField) -> String {
+        let optional =
+            |value: &Option<String>| value.clone().unwrap_or_else(|| "<unset>".to_string());
+        match field {
+            InputField::Owner => self.owner.clone(),
+            InputField::Tap => self.tap.clone(),
+            InputField::RepoName => self.repo_name.clone(),
+            InputField::Visibility => value_name(self.visibility),
+            InputField::Branch => self.branch.clone(),
+            InputField::FormulaMode => value_name(self.formula_mode),
+            InputField::FormulaUrl => optional(&self.formula_url),
+            InputField::FormulaName => optional(&self.formula_name),
+            InputField::PythonLock => optional(&self.python_lock),
+            InputField::Python => optional(&self.python),
+            InputField::SourceKind => value_name(self.source_kind),
+            InputField::SourceTag => optional(&self.source_tag),
+            InputField::HeadBranch => optional(&self.head_branch),
+            InputField::Service => self
+                .service
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_else(|| "<unset>".to_string()),
+            InputField::Caveats => optional(&self.caveats),
+            InputField::Completions => value_name(self.completions),
+            InputField::Manpage => optional(&self.manpage),
+            InputField::TestStrategy => value_name(self.test_strategy),
+            InputField::TestCommand => optional(&self.test_command),
+            InputField::TestExpect => optional(&self.test_expect),
+            InputField::License => self
+                .license
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_else(|| "<detect>".to_string()),
+            InputField::DependsOn if self.depends_on.is_empty() => "<unset>".to_string(),
+            InputField::DependsOn => self
+                .depends_on
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", "),
+            InputField::Artifacts if self.artifacts.is_empty() => "<unset>".to_string(),
+            InputField::Artifacts => self
+                .artifacts
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", "),
+        }
+    }
+
+    pub fn changed_fields(&self, other: &Inputs) -> Vec<InputField> {
+        InputField::ALL
+            .iter()
+            .copied()
+            .filter(|field| self.field_value(*field) != other.field_value(*field))
+            .collect()
+    }
 }
 
-fn normalize_token(label: &str, value: String) -> Result<String> {
-    let trimmed = value.trim();
+fn value_name<T: ValueEnum>(value: T) -> String {
+    value
+        .to_possible_value()
+        .map(|possible| possible.get_name().to_string())
+        .unwrap_or_default()
+}
 
-    if trimmed.is_empty() {
-        bail!("{} is required", label);
+fn normalize_artifacts(
+    mut artifacts: Vec<PlatformArtifact>,
+    formula_mode: FormulaMode,
+) -> Result<Vec<PlatformArtifact>> {
+    if artifacts.is_empty() {
+        return Ok(artifacts);
     }
 
-    if trimmed.contains('/') {
-        bail!("{} must not include '/'", label);
+    if formula_mode != FormulaMode::Stub {
+        bail!(
+            "--artifact is only supported with formula-mode stub (github-release finds its own assets)"
+        );
     }
 
-    if trimmed.chars().any(|ch| ch.is_whitespace()) {
-        bail!("{} must not contain whitespace", label);
+    artifacts.sort_by_key(|artifact| artifact.platform);
+    for pair in artifacts.windows(2) {
+        if pair[0].platform == pair[1].platform {
+            bail!(
+                "--artifact given twice for {}",
+                value_name(pair[0].platform)
+            );
+        }
     }
 
-    Ok(trimmed.to_string())
+    for artifact in &artifacts {
+        if !(artifact.url.starts_with("https://") || artifact.url.starts_with("http://")) {
+            bail!("--artifact {} must be an http(s) URL", artifact);
+        }
+    }
+
+    Ok(artifacts)
 }
 
-fn normalize_branch(branch: String) -> Result<String> {
-    let trimmed = branch.trim();
+fn normalize_python_lock(path: String, formula_mode: FormulaMode) -> Result<String> {
+    if formula_mode != FormulaMode::Python {
+        bail!("--python-lock is only supported with formula-mode python");
+    }
 
-    if trimmed.is_empty() {
-        bail!("branch is required");
+    let path = Path::new(path.trim());
+    if path
+        .file_name()
+        .is_some_and(|name| name == "pyproject.toml")
+    {
+        bail!(
+            "--python-lock needs pinned requirements (e.g. `uv export --format requirements-txt`), not pyproject.toml"
+        );
     }
 
+    let path = std::path::absolute(path)
+        .with_context(|| format!("invalid --python-lock path: {}", path.display()))?;
+    Ok(path.to_string_lossy().into_owned())
+}
+
+fn normalize_git_ref(value: String, check: fn(&str) -> Result<()>) -> Result<String> {
+    let trimmed = value.trim();
+    check(trimmed)?;
+    Ok(trimmed.to_string())
+}
+
+pub fn normalize_owner(value: String) -> Result<String> {
+    let trimmed = value.trim();
+    validate::github_login("owner", trimmed)?;
+    Ok(trimmed.to_string())
+}
+
+pub fn normalize_tap(value: String) -> Result<String> {
+    let trimmed = value.trim();
+    validate::github_repo_name("tap", trimmed)?;
+    Ok(trimmed.to_string())
+}
+
+pub fn normalize_repo_name(value: String) -> Result<String> {
+    let trimmed = value.trim();
+    validate::github_repo_name("repo name", trimmed)?;
+    Ok(trimmed.to_string())
+}
+
+pub fn normalize_formula_name(value: String) -> Result<String> {
+    let trimmed = value.trim();
+    validate::formula_name(trimmed)?;
+    Ok(trimmed.to_string())
+}
+
+pub fn normalize_branch(branch: String) -> Result<String> {
+    let trimmed = branch.trim();
+    validate::branch(trimmed)?;
     Ok(trimmed.to_string())
 }
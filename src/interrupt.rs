@@ -0,0 +1,65 @@
+use anyhow::{Context, Result};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Exit code used when a run stops because of Ctrl-C (128 + SIGINT).
+pub const EXIT_CODE: i32 = 130;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Installs a Ctrl-C handler that lets the runner stop cleanly: the running
+/// child gets the interrupt forwarded, and the step is recorded as interrupted.
+/// A second Ctrl-C exits immediately.
+pub fn install() -> Result<()> {
+    ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            eprintln!("\nSecond interrupt received; exiting without saving state.");
+            std::process::exit(EXIT_CODE);
+        }
+        eprintln!("\nInterrupt received; stopping the current command and saving state...");
+    })
+    .context("failed to install Ctrl-C handler")
+}
+
+pub fn requested() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+pub fn check() -> Result<()> {
+    if requested() {
+        return Err(Interrupted.into());
+    }
+
+    Ok(())
+}
+
+#[derive(Debug)]
+pub struct Interrupted;
+
+impl fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "interrupted by user")
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+pub fn is_interrupted(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| cause.is::<Interrupted>())
+}
+
+/// Sends SIGINT to a child process so it can clean up before exiting.
+#[cfg(unix)]
+pub fn forward(child: &std::process::Child) {
+    if let Ok(pid) = libc::pid_t::try_from(child.id()) {
+        // SAFETY: kill(2) has no memory-safety preconditions; a stale pid only yields ESRCH.
+        unsafe {
+            libc::kill(pid, libc::SIGINT);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn forward(child: &std::process::Child) {
+    let _ = child;
+}
@@ -0,0 +1,535 @@
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::io::Read;
+use std::str::FromStr;
+
+/// License files larger than this are not license texts.
+const MAX_LICENSE_SIZE: u64 = 256 * 1024;
+
+/// SPDX license identifiers accepted by `--license` (from the SPDX license
+/// list: the OSI-approved licenses and others common in Homebrew formulae).
+const SPDX_IDS: &[&str] = &[
+    "0BSD",
+    "AFL-3.0",
+    "AGPL-3.0-only",
+    "AGPL-3.0-or-later",
+    "Apache-1.1",
+    "Apache-2.0",
+    "APSL-2.0",
+    "Artistic-1.0",
+    "Artistic-1.0-Perl",
+    "Artistic-2.0",
+    "Beerware",
+    "BlueOak-1.0.0",
+    "BSD-1-Clause",
+    "BSD-2-Clause",
+    "BSD-2-Clause-Patent",
+    "BSD-3-Clause",
+    "BSD-3-Clause-Clear",
+    "BSD-4-Clause",
+    "BSL-1.0",
+    "bzip2-1.0.6",
+    "CC-BY-3.0",
+    "CC-BY-4.0",
+    "CC-BY-SA-3.0",
+    "CC-BY-SA-4.0",
+    "CC0-1.0",
+    "CDDL-1.0",
+    "CDDL-1.1",
+    "CECILL-2.1",
+    "CPL-1.0",
+    "curl",
+    "ECL-2.0",
+    "EPL-1.0",
+    "EPL-2.0",
+    "EUPL-1.1",
+    "EUPL-1.2",
+    "FSFAP",
+    "FTL",
+    "GFDL-1.3-only",
+    "GFDL-1.3-or-later",
+    "GPL-1.0-or-later",
+    "GPL-2.0-only",
+    "GPL-2.0-or-later",
+    "GPL-3.0-only",
+    "GPL-3.0-or-later",
+    "HPND",
+    "ICU",
+    "IJG",
+    "Imlib2",
+    "IPA",
+    "ISC",
+    "JSON",
+    "LGPL-2.0-only",
+    "LGPL-2.0-or-later",
+    "LGPL-2.1-only",
+    "LGPL-2.1-or-later",
+    "LGPL-3.0-only",
+    "LGPL-3.0-or-later",
+    "Libpng",
+    "libpng-2.0",
+    "libtiff",
+    "LPPL-1.3c",
+    "MirOS",
+    "MIT",
+    "MIT-0",
+    "MIT-CMU",
+    "MPL-1.1",
+    "MPL-2.0",
+    "MPL-2.0-no-copyleft-exception",
+    "MS-PL",
+    "MS-RL",
+    "MulanPSL-2.0",
+    "NCSA",
+    "OFL-1.1",
+    "OLDAP-2.8",
+    "OpenSSL",
+    "OSL-3.0",
+    "PHP-3.01",
+    "PostgreSQL",
+    "PSF-2.0",
+    "Python-2.0",
+    "Ruby",
+    "SGI-B-2.0",
+    "Sleepycat",
+    "SSPL-1.0",
+    "TCL",
+    "Unicode-3.0",
+    "Unicode-DFS-2016",
+    "Unlicense",
+    "UPL-1.0",
+    "Vim",
+    "W3C",
+    "WTFPL",
+    "X11",
+    "XFree86-1.1",
+    "Zlib",
+    "ZPL-2.1",
+];
+
+/// A formula's `license` stanza.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum License {
+    Single(String),
+    /// The user may pick any of them (dual licensing).
+    AnyOf(Vec<String>),
+    /// All of them apply.
+    AllOf(Vec<String>),
+}
+
+impl License {
+    pub fn ids(&self) -> &[String] {
+        match self {
+            License::Single(id) => std::slice::from_ref(id),
+            License::AnyOf(ids) | License::AllOf(ids) => ids,
+        }
+    }
+
+    /// The Ruby stanza, e.g. `license any_of: ["MIT", "Apache-2.0"]`.
+    pub fn stanza(&self) -> String {
+        let list = |ids: &[String]| {
+            ids.iter()
+                .map(|id| format!("\"{id}\""))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self {
+            License::Single(id) => format!("license \"{id}\""),
+            License::AnyOf(ids) => format!("license any_of: [{}]", list(ids)),
+            License::AllOf(ids) => format!("license all_of: [{}]", list(ids)),
+        }
+    }
+
+    /// Reads back a one-line stanza written by `stanza`.
+    pub fn from_stanza(line: &str) -> Option<Self> {
+        let rest = line.trim().strip_prefix("license ")?.trim();
+        let quoted = |text: &str| -> Option<Vec<String>> {
+            let inner = text.trim().strip_prefix('[')?.strip_suffix(']')?;
+            inner
+                .split(',')
+                .map(|id| {
+                    id.trim()
+                        .strip_prefix('"')?
+                        .strip_suffix('"')
+                        .map(str::to_string)
+                })
+                .collect()
+        };
+        if let Some(list) = rest.strip_prefix("any_of:") {
+            return quoted(list).map(License::AnyOf);
+        }
+        if let Some(list) = rest.strip_prefix("all_of:") {
+            return quoted(list).map(License::AllOf);
+        }
+        let id = rest.strip_prefix('"')?.strip_suffix('"')?;
+        Some(License::Single(id.to_string()))
+    }
+}
+
+impl FromStr for License {
+    type Err = String;
+
+    /// Parses an SPDX expression: one identifier, or identifiers joined by
+    /// only `OR` or only `AND`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = value.split_whitespace().collect();
+        if words.is_empty() {
+            return Err("license is empty".to_string());
+        }
+
+        let operators: BTreeSet<&str> = words.iter().skip(1).step_by(2).copied().collect();
+        let ids: Vec<String> = words
+            .iter()
+            .step_by(2)
+            .map(|id| spdx_id(id))
+            .collect::<Result<_, _>>()?;
+        match operators.into_iter().collect::<Vec<_>>()[..] {
+            [] => Ok(License::Single(ids[0].clone())),
+            ["OR"] if words.len() % 2 == 1 => Ok(License::AnyOf(ids)),
+            ["AND"] if words.len() % 2 == 1 => Ok(License::AllOf(ids)),
+            _ => Err(format!(
+                "'{value}' is not supported; use one SPDX identifier, or identifiers joined by only OR or only AND"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for License {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            License::Single(id) => write!(f, "{id}"),
+            License::AnyOf(ids) => write!(f, "{}", ids.join(" OR ")),
+            License::AllOf(ids) => write!(f, "{}", ids.join(" AND ")),
+        }
+    }
+}
+
+impl TryFrom<String> for License {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<License> for String {
+    fn from(license: License) -> Self {
+        license.to_string()
+    }
+}
+
+/// The canonical spelling of an SPDX identifier, matched case-insensitively.
+fn spdx_id(id: &str) -> Result<String, String> {
+    if let Some(found) = SPDX_IDS.iter().find(|known| known.eq_ignore_ascii_case(id)) {
+        return Ok(found.to_string());
+    }
+
+    let base = id.trim_end_matches('+');
+    if SPDX_IDS
+        .iter()
+        .any(|known| known.eq_ignore_ascii_case(&format!("{base}-only")))
+    {
+        return Err(format!(
+            "'{id}' is deprecated in SPDX; use {base}-only or {base}-or-later"
+        ));
+    }
+    Err(format!("'{id}' is not an SPDX license identifier"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Confidence {
+    High,
+    Low,
+}
+
+/// The license a formula was given and where it came from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Detection {
+    pub license: License,
+    pub confidence: Confidence,
+    /// `--license`, `formula`, or the license files it was read from.
+    pub source: String,
+    /// Why a detection has low confidence.
+    pub note: Option<String>,
+}
+
+impl Detection {
+    pub fn explicit(license: License) -> Self {
+        Self {
+            license,
+            confidence: Confidence::High,
+            source: "--license".to_string(),
+            note: None,
+        }
+    }
+}
+
+/// Finds LICENSE/COPYING files at the top of a `.tar.gz` archive held in
+/// memory and classifies them. `None` when the archive has no license file
+/// that could be classified.
+pub fn detect(archive: &[u8]) -> Result<Option<Detection>> {
+    let mut tar = tar::Archive::new(GzDecoder::new(archive));
+    let mut files: Vec<(String, String)> = Vec::new();
+    for entry in tar.entries().context("not a .tar.gz archive")? {
+        let mut entry = entry.context("failed to read archive")?;
+        if !entry.header().entry_type().is_file() || entry.size() > MAX_LICENSE_SIZE {
+            continue;
+        }
+        let path = entry.path().context("failed to read archive")?;
+        // `LICENSE` or `<project>-<version>/LICENSE`; deeper ones belong to
+        // vendored code.
+        if path.components().count() > 2 {
+            continue;
+        }
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if !is_license_file(name) {
+            continue;
+        }
+        let name = name.to_string();
+        let mut text = String::new();
+        if entry.read_to_string(&mut text).is_ok() {
+            files.push((name, text));
+        }
+    }
+    files.sort();
+    Ok(combine(&files))
+}
+
+fn is_license_file(name: &str) -> bool {
+    let upper = name.to_ascii_uppercase();
+    ["LICENSE", "LICENCE", "COPYING", "UNLICENSE"]
+        .iter()
+        .any(|prefix| upper.starts_with(prefix))
+}
+
+fn combine(files: &[(String, String)]) -> Option<Detection> {
+    let mut ids: BTreeSet<&'static str> = BTreeSet::new();
+    let mut notes: Vec<String> = Vec::new();
+    let mut unclassified: Vec<&str> = Vec::new();
+    for (name, text) in files {
+        let found = classify(text);
+        if found.is_empty() {
+            unclassified.push(name);
+        }
+        for (id, note) in found {
+            ids.insert(id);
+            if let Some(note) = note {
+                notes.push(note.to_string());
+            }
+        }
+    }
+
+    // The LGPL is distributed together with the GPL text it amends.
+    for (lgpl, gpl) in [
+        ("LGPL-2.1-or-later", "GPL-2.0-or-later"),
+        ("LGPL-3.0-or-later", "GPL-3.0-or-later"),
+    ] {
+        if ids.contains(lgpl) {
+            ids.remove(gpl);
+        }
+    }
+
+    let ids: Vec<String> = ids.into_iter().map(str::to_string).collect();
+    let license = match ids.len() {
+        0 => return None,
+        1 => License::Single(ids[0].clone()),
+        _ => {
+            // `LICENSE-MIT` + `LICENSE-APACHE` is the usual way to ship a
+            // dual license; anything else may be bundled third-party terms.
+            let per_license_files = files.iter().all(|(name, _)| {
+                let upper = name.to_ascii_uppercase();
+                upper.starts_with("LICENSE-") || upper.starts_with("LICENSE.")
+            });
+            if !per_license_files {
+                notes.push("several licenses found; they may cover bundled code".to_string());
+            }
+            License::AnyOf(ids)
+        }
+    };
+    if !unclassified.is_empty() {
+        notes.push(format!("could not classify {}", unclassified.join(", ")));
+    }
+    notes.dedup();
+
+    Some(Detection {
+        license,
+        confidence: if notes.is_empty() {
+            Confidence::High
+        } else {
+            Confidence::Low
+        },
+        source: files
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(", "),
+        note: (!notes.is_empty()).then(|| notes.join("; ")),
+    })
+}
+
+const GPL_VERSION_NOTE: &str =
+    "the license text does not say whether later versions apply; check the source headers";
+
+/// SPDX identifiers whose text `text` contains, each with a note when the
+/// text alone cannot settle the identifier.
+fn classify(text: &str) -> Vec<(&'static str, Option<&'static str>)> {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let lower = text.to_ascii_lowercase();
+    let has = |phrase: &str| lower.contains(phrase);
+    let mut found = Vec::new();
+
+    // The GNU licenses mention each other, so only their titles count.
+    let title: String = lower.chars().take(300).collect();
+    let titled = |phrase: &str| title.contains(phrase);
+    if titled("gnu affero general public license") {
+        found.push(("AGPL-3.0-or-later", Some(GPL_VERSION_NOTE)));
+    } else if titled("gnu lesser general public license")
+        || titled("gnu library general public license")
+    {
+        if titled("version 3") {
+            found.push(("LGPL-3.0-or-later", Some(GPL_VERSION_NOTE)));
+        } else {
+            found.push(("LGPL-2.1-or-later", Some(GPL_VERSION_NOTE)));
+        }
+    } else if titled("gnu general public license") {
+        if titled("version 3") {
+            found.push(("GPL-3.0-or-later", Some(GPL_VERSION_NOTE)));
+        } else {
+            found.push(("GPL-2.0-or-later", Some(GPL_VERSION_NOTE)));
+        }
+    }
+
+    if has("apache license") && has("version 2.0") {
+        found.push(("Apache-2.0", None));
+    }
+    if has("mozilla public license") && (has("version 2.0") || has("v. 2.0")) {
+        found.push(("MPL-2.0", None));
+    }
+    if has("permission is hereby granted, free of charge") {
+        if has("the above copyright notice and this permission notice shall be included") {
+            found.push(("MIT", None));
+        } else {
+            found.push(("MIT-0", None));
+        }
+    }
+    if has("redistribution and use in source and binary forms") {
+        if has("all advertising materials") {
+            found.push(("BSD-4-Clause", None));
+        } else if has("neither the name") || has("may not be used to endorse") {
+            found.push(("BSD-3-Clause", None));
+        } else {
+            found.push(("BSD-2-Clause", None));
+        }
+    }
+    if has("permission to use, copy, modify, and/or distribute this software for any purpose") {
+        if has("provided that the above copyright notice and this permission notice appear") {
+            found.push(("ISC", None));
+        } else {
+            found.push(("0BSD", None));
+        }
+    }
+    if has("this is free and unencumbered software released into the public domain") {
+        found.push(("Unlicense", None));
+    }
+    if has("boost software license") {
+        found.push(("BSL-1.0", None));
+    }
+    if has("eclipse public license") && (has("v 2.0") || has("version 2.0")) {
+        found.push(("EPL-2.0", None));
+    }
+    if has("cc0 1.0 universal") {
+        found.push(("CC0-1.0", None));
+    }
+    if has("the artistic license 2.0") {
+        found.push(("Artistic-2.0", None));
+    }
+    if has("do what the fuck you want to public license") {
+        found.push(("WTFPL", None));
+    }
+    if has("provided 'as-is', without any express or implied warranty")
+        && has("must not be misrepresented")
+    {
+        found.push(("Zlib", None));
+    }
+    found
+}
+
+/// Whether the formula's license is missing or a placeholder (`"TODO"`,
+/// `""`, or commented out).
+pub fn has_placeholder(source: &str) -> bool {
+    match license_line(source) {
+        Some(line) => matches!(
+            License::from_stanza(line),
+            Some(License::Single(id)) if id.is_empty() || id == "TODO"
+        ),
+        None => true,
+    }
+}
+
+/// The top-level `license` stanza, as written in the formula.
+pub fn declared(source: &str) -> Option<License> {
+    license_line(source).and_then(License::from_stanza)
+}
+
+fn license_line(source: &str) -> Option<&str> {
+    source.lines().find(|line| line.starts_with("  license "))
+}
+
+/// Replaces the formula's `license` line, or adds one at the end of the
+/// header that starts at `desc` (after `url`/`version`/`sha256`, before
+/// `head`).
+pub fn set_license(source: &str, license: &License) -> String {
+    let stanza = format!("  {}", license.stanza());
+    let lines: Vec<&str> = source.lines().collect();
+
+    let index = match lines.iter().position(|line| line.starts_with("  license ")) {
+        Some(index) => {
+            let mut end = index;
+            // A wrapped `license any_of: [...]` continues until its `]`.
+            while lines[index].contains('[') && !lines[end].contains(']') && end + 1 < lines.len() {
+                end += 1;
+            }
+            let mut out: Vec<&str> = lines[..index].to_vec();
+            out.push(&stanza);
+            out.extend(&lines[end + 1..]);
+            return join(&out);
+        }
+        None => {
+            let start = lines
+                .iter()
+                .position(|line| line.starts_with("  desc "))
+                .or_else(|| {
+                    lines
+                        .iter()
+                        .position(|line| line.starts_with("class "))
+                        .map(|index| index + 1)
+                })
+                .unwrap_or(0);
+            lines[start..]
+                .iter()
+                .position(|line| line.trim().is_empty() || line.starts_with("  head "))
+                .map_or(lines.len(), |offset| start + offset)
+        }
+    };
+
+    let mut out: Vec<&str> = lines[..index].to_vec();
+    out.push(&stanza);
+    out.extend(&lines[index..]);
+    join(&out)
+}
+
+fn join(lines: &[&str]) -> String {
+    let mut out = String::new();
+    for line in lines {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
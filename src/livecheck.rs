@@ -0,0 +1,240 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::exec::{self, CommandExt};
+use crate::formula;
+use crate::upstream::{self, Checker, CurlClient, Endpoints};
+
+/// One entry of `brew livecheck --json` output.
+#[derive(Debug, Deserialize)]
+struct BrewEntry {
+    formula: Option<String>,
+    status: Option<String>,
+    #[serde(default)]
+    messages: Vec<String>,
+    version: Option<BrewVersion>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BrewVersion {
+    current: Option<String>,
+    latest: Option<String>,
+    #[serde(default)]
+    outdated: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Status {
+    Outdated,
+    UpToDate,
+    Skipped,
+    Error,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FormulaStatus {
+    pub formula: String,
+    pub current: Option<String>,
+    pub latest: Option<String>,
+    pub status: Status,
+    pub message: Option<String>,
+}
+
+/// Runs `brew livecheck` for every formula in `tap` and reports the ones that
+/// are outdated or could not be checked (or all of them with `all`).
+pub fn run(tap: &str, json: bool, all: bool) -> Result<()> {
+    let output = Command::new("brew")
+        .args(["livecheck", "--tap", tap, "--json", "--quiet"])
+        .output_within(exec::APPLY_TIMEOUT)
+        .context("failed to run brew livecheck")?;
+
+    let entries: Vec<BrewEntry> = serde_json::from_slice(&output.stdout).with_context(|| {
+        format!(
+            "failed to parse brew livecheck output (status {:?}): {}",
+            output.status.code(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+    })?;
+
+    let results = entries.into_iter().map(FormulaStatus::from).collect();
+    report(tap, results, json, all)
+}
+
+/// Checks formulae in a tap checkout without brew, using the native
+/// strategies in `upstream`.
+pub fn run_native(tap_dir: &Path, json: bool, all: bool) -> Result<()> {
+    let checker = Checker::new(&CurlClient, Endpoints::from_env());
+    let results = formula_files(tap_dir)?
+        .iter()
+        .map(|path| check_native(&checker, path))
+        .collect();
+
+    report(&tap_dir.display().to_string(), results, json, all)
+}
+
+fn check_native(checker: &Checker, path: &Path) -> FormulaStatus {
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut result = FormulaStatus {
+        formula: name,
+        current: None,
+        latest: None,
+        status: Status::Error,
+        message: None,
+    };
+
+    let source = match fs::read_to_string(path) {
+        Ok(source) => formula::parse(&source),
+        Err(err) => {
+            result.message = Some(format!("failed to read {}: {}", path.display(), err));
+            return result;
+        }
+    };
+    result.current = source.current_version();
+
+    let Some(strategy) = checker.strategy(&source) else {
+        result.status = Status::Skipped;
+        result.message = Some("no native livecheck strategy for this URL".to_string());
+        return result;
+    };
+
+    match checker.latest(&source, strategy) {
+        Ok(Some(latest)) => {
+            let newer = result.current.as_deref().is_none_or(|current| {
+                upstream::compare_versions(&latest, current) == Ordering::Greater
+            });
+            result.status = if newer {
+                Status::Outdated
+            } else {
+                Status::UpToDate
+            };
+            result.latest = Some(latest);
+        }
+        Ok(None) => {
+            result.message = Some(format!("{} found no versions", strategy.name()));
+        }
+        Err(err) => {
+            result.message = Some(format!("{}: {:#}", strategy.name(), err));
+        }
+    }
+
+    result
+}
+
+/// `Formula/*.rb`, including sharded `Formula/<letter>/*.rb`; a directory
+/// without `Formula/` is searched directly.
+fn formula_files(tap_dir: &Path) -> Result<Vec<PathBuf>> {
+    let formula_dir = tap_dir.join("Formula");
+    let root = if formula_dir.is_dir() {
+        formula_dir
+    } else {
+        tap_dir.to_path_buf()
+    };
+
+    let mut files = Vec::new();
+    let mut dirs = vec![(root, 0)];
+    while let Some((dir, depth)) = dirs.pop() {
+        for entry in fs::read_dir(&dir)
+            .with_context(|| format!("failed to read formula directory: {}", dir.display()))?
+        {
+            let path = entry?.path();
+            if path.is_dir() && depth == 0 {
+                dirs.push((path, depth + 1));
+            } else if path.extension().is_some_and(|ext| ext == "rb") {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+fn report(label: &str, results: Vec<FormulaStatus>, json: bool, all: bool) -> Result<()> {
+    let results: Vec<FormulaStatus> = results
+        .into_iter()
+        .filter(|result| all || result.status != Status::UpToDate)
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&results)?);
+    } else {
+        print_table(label, &results);
+    }
+
+    Ok(())
+}
+
+impl From<BrewEntry> for FormulaStatus {
+    fn from(entry: BrewEntry) -> Self {
+        let status = match entry.status.as_deref() {
+            Some("error") => Status::Error,
+            Some("skipped") => Status::Skipped,
+            _ if entry
+                .version
+                .as_ref()
+                .is_some_and(|version| version.outdated) =>
+            {
+                Status::Outdated
+            }
+            _ => Status::UpToDate,
+        };
+        let (current, latest) = entry
+            .version
+            .map(|version| (version.current, version.latest))
+            .unwrap_or_default();
+
+        Self {
+            formula: entry.formula.unwrap_or_else(|| "<unknown>".to_string()),
+            current,
+            latest,
+            status,
+            message: (!entry.messages.is_empty()).then(|| entry.messages.join("; ")),
+        }
+    }
+}
+
+fn print_table(label: &str, results: &[FormulaStatus]) {
+    if results.is_empty() {
+        println!("All formulae in {} are up to date", label);
+        return;
+    }
+
+    let width = results
+        .iter()
+        .map(|result| result.formula.len())
+        .max()
+        .unwrap_or(0)
+        .max("FORMULA".len());
+    let dash = "-".to_string();
+
+    println!(
+        "{:<width$}  {:<12}  {:<12}  STATUS",
+        "FORMULA", "CURRENT", "LATEST"
+    );
+    for result in results {
+        let status = match result.status {
+            Status::Outdated => "outdated",
+            Status::UpToDate => "up to date",
+            Status::Skipped => "skipped",
+            Status::Error => "error",
+        };
+        println!(
+            "{:<width$}  {:<12}  {:<12}  {}",
+            result.formula,
+            result.current.as_ref().unwrap_or(&dash),
+            result.latest.as_ref().unwrap_or(&dash),
+            status
+        );
+        if let Some(message) = &result.message {
+            println!("{:<width$}  {}", "", message);
+        }
+    }
+}
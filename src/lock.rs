@@ -0,0 +1,115 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use crate::state::now_rfc3339;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LockInfo {
+    pid: u32,
+    started_at: String,
+    run_id: String,
+}
+
+/// Advisory lock backed by a file created with `O_EXCL`; removed on drop.
+#[derive(Debug)]
+pub struct LockGuard {
+    path: PathBuf,
+}
+
+impl Drop for LockGuard {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+pub fn acquire(path: &Path, label: &str, run_id: &str, force_unlock: bool) -> Result<LockGuard> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create lock directory: {}", parent.display()))?;
+    }
+
+    let info = LockInfo {
+        pid: std::process::id(),
+        started_at: now_rfc3339(),
+        run_id: run_id.to_string(),
+    };
+
+    match try_create(path, &info) {
+        Ok(()) => {
+            return Ok(LockGuard {
+                path: path.to_path_buf(),
+            })
+        }
+        Err(err) if err.kind() == ErrorKind::AlreadyExists => {}
+        Err(err) => {
+            return Err(err).with_context(|| format!("Failed to create lock: {}", path.display()))
+        }
+    }
+
+    let holder = describe_holder(path);
+    if !force_unlock {
+        anyhow::bail!(
+            "{} is locked by {}.\nIf that process is no longer running, re-run with --force-unlock (lock file: {}).",
+            label,
+            holder,
+            path.display()
+        );
+    }
+
+    eprintln!("Warning: removing lock on {} held by {}", label, holder);
+    fs::remove_file(path)
+        .with_context(|| format!("Failed to remove stale lock: {}", path.display()))?;
+    try_create(path, &info)
+        .with_context(|| format!("Failed to create lock: {}", path.display()))?;
+
+    Ok(LockGuard {
+        path: path.to_path_buf(),
+    })
+}
+
+fn try_create(path: &Path, info: &LockInfo) -> std::io::Result<()> {
+    let mut file = OpenOptions::new().write(true).create_new(true).open(path)?;
+    let data = serde_json::to_vec_pretty(info)?;
+    file.write_all(&data)?;
+    file.sync_all()
+}
+
+fn describe_holder(path: &Path) -> String {
+    let info = fs::read(path)
+        .ok()
+        .and_then(|data| serde_json::from_slice::<LockInfo>(&data).ok());
+
+    match info {
+        Some(info) => {
+            let liveness = if process_alive(info.pid) {
+                ""
+            } else {
+                ", no longer running"
+            };
+            format!(
+                "PID {} (run {}, started {}{})",
+                info.pid, info.run_id, info.started_at, liveness
+            )
+        }
+        None => "an unknown process (unreadable lock file)".to_string(),
+    }
+}
+
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+
+    // SAFETY: signal 0 only checks whether the process exists and can be signalled.
+    let result = unsafe { libc::kill(pid, 0) };
+    result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn process_alive(_pid: u32) -> bool {
+    true
+}
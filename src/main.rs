@@ -1,14 +1,42 @@
+mod binary_formula;
+mod completions;
+mod config;
+mod dependency;
+mod exec;
+mod forge;
+mod formula;
+mod formula_test;
 mod inputs;
+mod interrupt;
+mod license;
+mod livecheck;
+mod lock;
+mod migrations;
+mod plan;
+mod python;
 mod runner;
+mod runs;
+mod service;
+mod source_url;
 mod state;
 mod steps;
+mod upstream;
+mod validate;
+mod wizard;
 
 use anyhow::Result;
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
 
-use crate::inputs::{FormulaMode, Inputs, Visibility};
-use crate::runner::Runner;
-use crate::state::RunContext;
+use crate::config::Config;
+use crate::dependency::Dependency;
+use crate::inputs::{
+    Completions, FormulaMode, InputOverrides, Inputs, PlatformArtifact, SourceKind, TestStrategy,
+    Visibility,
+};
+use crate::license::License;
+use crate::runner::{Runner, StepSelection};
+use crate::state::{RunContext, StateStore, APP_NAME, STATE_DIR_ENV};
 use crate::steps::add_formula::AddFormulaStep;
 use crate::steps::brew_tap_new::BrewTapNewStep;
 use crate::steps::commit_and_push::CommitAndPushStep;
@@ -18,8 +46,84 @@ use crate::steps::preflight::PreflightStep;
 use crate::steps::validate_tap::ValidateTapStep;
 
 #[derive(Parser, Debug)]
-#[command(author, version, about = "Homebrew tap setup helper")]
+#[command(
+    author,
+    version,
+    about = "Homebrew tap setup helper",
+    args_conflicts_with_subcommands = true
+)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[arg(
+        long,
+        global = true,
+        env = STATE_DIR_ENV,
+        help = "Directory for run state and locks [default: platform state dir]"
+    )]
+    state_dir: Option<PathBuf>,
+
+    #[command(flatten)]
+    run: RunArgs,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Show what each incomplete step would do and save the plan for the run
+    Plan(Box<RunArgs>),
+    /// Report formulae in a tap whose upstream has a newer version
+    Livecheck {
+        #[arg(
+            help = "Tap to check with brew, as <owner>/<tap>",
+            required_unless_present = "path"
+        )]
+        tap: Option<String>,
+
+        #[arg(
+            long,
+            conflicts_with = "tap",
+            help = "Check a tap checkout without brew, using built-in strategies"
+        )]
+        path: Option<PathBuf>,
+
+        #[arg(long, help = "Print results as JSON")]
+        json: bool,
+
+        #[arg(long, help = "Include formulae that are up to date")]
+        all: bool,
+    },
+    /// Regenerate a Python formula's resource blocks from a requirements lock
+    UpdateResources {
+        #[arg(help = "Formula file to update")]
+        formula: PathBuf,
+
+        #[arg(long, value_name = "FILE", help = "Pinned requirements lock")]
+        lock: PathBuf,
+    },
+    /// Inspect stored runs
+    Runs {
+        #[command(subcommand)]
+        command: RunsCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum RunsCommand {
+    /// Show step statuses, retry attempts and errors for a run
+    Show {
+        run_id: String,
+
+        #[arg(
+            long,
+            help = "Show the state as of history snapshot <n>, as listed by `runs show`"
+        )]
+        at: Option<usize>,
+    },
+}
+
+#[derive(Args, Debug)]
+struct RunArgs {
     #[arg(
         long,
         default_value_t = false,
@@ -27,7 +131,10 @@ struct Cli {
     )]
     dry_run: bool,
 
-    #[arg(long, help = "Resume a previous run by ID")]
+    #[arg(
+        long,
+        help = "Resume a previous run by ID (input flags given here override the stored inputs)"
+    )]
     resume: Option<String>,
 
     #[arg(long, help = "GitHub owner or org for the tap repo")]
@@ -39,14 +146,14 @@ struct Cli {
     #[arg(long, help = "Override repo name (defaults to homebrew-<tap>)")]
     repo_name: Option<String>,
 
-    #[arg(long, value_enum, default_value_t = Visibility::Public)]
-    visibility: Visibility,
+    #[arg(long, value_enum, help = "Repo visibility [default: public]")]
+    visibility: Option<Visibility>,
 
-    #[arg(long, default_value = "main")]
-    branch: String,
+    #[arg(long, help = "Default branch [default: main]")]
+    branch: Option<String>,
 
-    #[arg(long, value_enum, default_value_t = FormulaMode::Stub)]
-    formula_mode: FormulaMode,
+    #[arg(long, value_enum, help = "How to create the formula [default: stub]")]
+    formula_mode: Option<FormulaMode>,
 
     #[arg(
         long,
@@ -56,32 +163,223 @@ struct Cli {
 
     #[arg(long, help = "Formula name to use with brew create (optional)")]
     formula_name: Option<String>,
+
+    #[arg(
+        long = "artifact",
+        value_name = "PLATFORM=URL",
+        help = "Prebuilt artifact for one platform (macos-arm64, macos-x86_64, linux-arm64, linux-x86_64); repeatable"
+    )]
+    artifacts: Vec<PlatformArtifact>,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Pinned requirements lock for python mode; each entry becomes a resource block"
+    )]
+    python_lock: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "FORMULA",
+        help = "Python formula for python mode, e.g. python@3.12 [default: the lock's Python, else python@3.13]"
+    )]
+    python: Option<String>,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "Where the formula source comes from [default: tarball]"
+    )]
+    source_kind: Option<SourceKind>,
+
+    #[arg(long, help = "Tag to pin for source-kind git-tag")]
+    source_tag: Option<String>,
+
+    #[arg(
+        long,
+        help = "Branch for the head stanza (source-kind head defaults to main)"
+    )]
+    head_branch: Option<String>,
+
+    #[arg(
+        long = "depends-on",
+        value_name = "NAME[:TYPE]",
+        help = "Dependency of the generated formula, e.g. rust:build, openssl@3, macos:monterey; repeatable"
+    )]
+    depends_on: Vec<Dependency>,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "JSON file with formula options (depends-on, service-*, caveats, completions, manpage, test-*, license); flags take precedence"
+    )]
+    config: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "COMMAND",
+        help = "Run the formula as a service: an installed binary and its arguments"
+    )]
+    service_run: Option<String>,
+
+    #[arg(
+        long,
+        num_args = 0..=1,
+        default_missing_value = "true",
+        value_name = "BOOL",
+        help = "Restart the service when it exits"
+    )]
+    service_keep_alive: Option<bool>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Service log file relative to var, e.g. log/mytool.log"
+    )]
+    service_log_path: Option<String>,
+
+    #[arg(long, value_name = "TEXT", help = "Caveats shown after installing")]
+    caveats: Option<String>,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "Generate shell completions with the tool's CLI framework [default: none]"
+    )]
+    completions: Option<Completions>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Man page to install, relative to the source tree (e.g. man/mytool.1)"
+    )]
+    manpage: Option<String>,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "What the generated test checks: cli (--version), library (links) or command [default: cli]"
+    )]
+    test_strategy: Option<TestStrategy>,
+
+    #[arg(
+        long,
+        value_name = "COMMAND",
+        help = "Command run by the command test strategy, starting with an installed binary"
+    )]
+    test_command: Option<String>,
+
+    #[arg(long, value_name = "TEXT", help = "Output the test command must print")]
+    test_expect: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "SPDX",
+        help = "SPDX license expression, e.g. MIT or \"MIT OR Apache-2.0\" [default: detected from the source archive]"
+    )]
+    license: Option<License>,
+
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "Run only these step IDs (comma-separated)"
+    )]
+    only: Vec<String>,
+
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "Skip these step IDs (comma-separated)"
+    )]
+    skip: Vec<String>,
+
+    #[arg(long, help = "Start at this step ID")]
+    from: Option<String>,
+
+    #[arg(long, help = "Stop after this step ID")]
+    until: Option<String>,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Refuse to run steps whose prerequisites have never completed"
+    )]
+    strict: bool,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Remove stale run/tap locks left by a crashed process"
+    )]
+    force_unlock: bool,
+}
+
+impl RunArgs {
+    fn overrides(&self) -> Result<InputOverrides> {
+        let config = match &self.config {
+            Some(path) => Config::load(path)?,
+            None => Config::default(),
+        };
+        let mut depends_on = config.depends_on;
+        depends_on.extend(self.depends_on.iter().cloned());
+
+        Ok(InputOverrides {
+            owner: self.owner.clone(),
+            tap: self.tap.clone(),
+            repo_name: self.repo_name.clone(),
+            visibility: self.visibility,
+            branch: self.branch.clone(),
+            formula_mode: self.formula_mode,
+            formula_url: self.formula_url.clone(),
+            formula_name: self.formula_name.clone(),
+            artifacts: self.artifacts.clone(),
+            python_lock: self
+                .python_lock
+                .as_ref()
+                .map(|path| path.to_string_lossy().into_owned()),
+            python: self.python.clone(),
+            source_kind: self.source_kind,
+            source_tag: self.source_tag.clone(),
+            head_branch: self.head_branch.clone(),
+            depends_on,
+            service_run: self.service_run.clone().or(config.service_run),
+            service_keep_alive: self.service_keep_alive.or(config.service_keep_alive),
+            service_log_path: self.service_log_path.clone().or(config.service_log_path),
+            caveats: self.caveats.clone().or(config.caveats),
+            completions: self.completions.or(config.completions),
+            manpage: self.manpage.clone().or(config.manpage),
+            test_strategy: self.test_strategy.or(config.test_strategy),
+            test_command: self.test_command.clone().or(config.test_command),
+            test_expect: self.test_expect.clone().or(config.test_expect),
+            license: self.license.clone().or(config.license),
+        })
+    }
+
+    fn selection(&self) -> StepSelection {
+        StepSelection {
+            only: self.only.clone(),
+            skip: self.skip.clone(),
+            from: self.from.clone(),
+            until: self.until.clone(),
+            strict: self.strict,
+        }
+    }
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
-    let mut ctx = if let Some(run_id) = cli.resume {
-        RunContext::load(run_id, cli.dry_run)?
-    } else {
-        let owner = cli
-            .owner
-            .ok_or_else(|| anyhow::anyhow!("--owner is required"))?;
-        let tap = cli
-            .tap
-            .ok_or_else(|| anyhow::anyhow!("--tap is required"))?;
-        let inputs = Inputs::new(
-            owner,
-            tap,
-            cli.repo_name,
-            cli.visibility,
-            cli.branch,
-            cli.formula_mode,
-            cli.formula_url,
-            cli.formula_name,
-        )?;
-        RunContext::new(cli.dry_run, inputs)?
-    };
-    let runner = Runner::new(vec![
+
+    match execute(cli) {
+        Err(err) if interrupt::is_interrupted(&err) => {
+            eprintln!("Error: {err:#}");
+            std::process::exit(interrupt::EXIT_CODE);
+        }
+        result => result,
+    }
+}
+
+fn execute(cli: Cli) -> Result<()> {
+    let mut runner = Runner::new(vec![
         Box::new(PreflightStep::new()),
         Box::new(BrewTapNewStep::new()),
         Box::new(GhRepoCreateStep::new()),
@@ -89,7 +387,73 @@ fn main() -> Result<()> {
         Box::new(CommitAndPushStep::new()),
         Box::new(ValidateTapStep::new()),
         Box::new(FinalSummaryStep::new()),
-    ]);
+    ])?;
+
+    let store = StateStore::new(APP_NAME, cli.state_dir)?;
+
+    // Runs install the Ctrl-C handler in `run_context`, after any wizard
+    // prompts, so that Ctrl-C at a prompt aborts right away.
+    if !matches!(cli.command, None | Some(Command::Plan(_))) {
+        interrupt::install()?;
+    }
+
+    match cli.command {
+        Some(Command::Plan(args)) => {
+            runner.select(&args.selection())?;
+            let mut ctx = run_context(store, *args, &runner)?;
+            let plan = runner.plan(&mut ctx)?;
+            plan.print();
+            ctx.state_store.write_plan(&ctx.run_id, &plan)?;
+
+            println!(
+                "\nPlan saved: {}",
+                ctx.state_store.plan_path(&ctx.run_id).display()
+            );
+            println!("Apply it with: --resume {}", ctx.run_id);
+            Ok(())
+        }
+        Some(Command::Livecheck {
+            tap,
+            path,
+            json,
+            all,
+        }) => match (tap, path) {
+            (_, Some(path)) => livecheck::run_native(&path, json, all),
+            (Some(tap), None) => livecheck::run(&tap, json, all),
+            (None, None) => anyhow::bail!("pass a tap (<owner>/<tap>) or --path"),
+        },
+        Some(Command::UpdateResources { formula, lock }) => {
+            python::update_resources(&formula, &lock)
+        }
+        Some(Command::Runs { command }) => match command {
+            RunsCommand::Show { run_id, at } => runs::show(&store, &run_id, at),
+        },
+        None => {
+            runner.select(&cli.run.selection())?;
+            let mut ctx = run_context(store, cli.run, &runner)?;
+            runner.run(&mut ctx)
+        }
+    }
+}
+
+fn run_context(store: StateStore, args: RunArgs, runner: &Runner) -> Result<RunContext> {
+    if let Some(run_id) = args.resume.clone() {
+        interrupt::install()?;
+        let mut ctx = RunContext::load(store, run_id, args.dry_run, args.force_unlock)?;
+        let changed = ctx.apply_overrides(args.overrides()?, args.force_unlock)?;
+        if !changed.is_empty() {
+            runner.invalidate(&mut ctx, &changed)?;
+        }
+        return Ok(ctx);
+    }
+
+    if (args.owner.is_none() || args.tap.is_none()) && wizard::available() {
+        let inputs = wizard::prompt_inputs(args.overrides()?)?;
+        interrupt::install()?;
+        return RunContext::new(store, args.dry_run, inputs, args.force_unlock);
+    }
 
-    runner.run(&mut ctx)
+    interrupt::install()?;
+    let inputs = Inputs::new(args.overrides()?)?;
+    RunContext::new(store, args.dry_run, inputs, args.force_unlock)
 }
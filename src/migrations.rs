@@ -0,0 +1,361 @@
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+
+use crate::state::SCHEMA_VERSION;
+
+type Migration = fn(&mut Map<String, Value>) -> Result<()>;
+
+/// Ordered migrations; entry `i` upgrades a state from schema `i + 1` to `i + 2`.
+const MIGRATIONS: &[Migration] = &[
+    v1_to_v2, v2_to_v3, v3_to_v4, v4_to_v5, v5_to_v6, v6_to_v7, v7_to_v8, v8_to_v9, v9_to_v10,
+    v10_to_v11,
+];
+
+/// Upgrades raw state JSON to the current schema version. Returns the migrated
+/// value and the version it started from.
+pub fn migrate(mut value: Value) -> Result<(Value, u32)> {
+    let object = value
+        .as_object_mut()
+        .context("state is not a JSON object")?;
+
+    let found = match object.get("schema_version") {
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .context("schema_version is not a valid number")?,
+        None => 1,
+    };
+
+    if found == 0 {
+        anyhow::bail!("schema_version 0 is not a valid state schema");
+    }
+
+    if found > SCHEMA_VERSION {
+        anyhow::bail!(
+            "state uses schema version {}, but this build only understands up to {}; upgrade homebrew-tap-setup to resume this run",
+            found,
+            SCHEMA_VERSION
+        );
+    }
+
+    for version in found..SCHEMA_VERSION {
+        let migration = MIGRATIONS[(version - 1) as usize];
+        migration(object)
+            .with_context(|| format!("failed to migrate state from schema {version}"))?;
+        object.insert("schema_version".to_string(), Value::from(version + 1));
+    }
+
+    Ok((value, found))
+}
+
+/// v2 makes fields that v1 left to `#[serde(default)]` explicit and adds
+/// per-step retry attempts.
+fn v1_to_v2(state: &mut Map<String, Value>) -> Result<()> {
+    for key in ["inputs", "tap_path", "formula_name"] {
+        state.entry(key).or_insert(Value::Null);
+    }
+    state.entry("summary_printed").or_insert(Value::Bool(false));
+
+    if let Some(steps) = state.get_mut("steps").and_then(Value::as_array_mut) {
+        for step in steps {
+            let step = step
+                .as_object_mut()
+                .context("step record is not a JSON object")?;
+            step.entry("attempts")
+                .or_insert_with(|| Value::Array(Vec::new()));
+        }
+    }
+
+    Ok(())
+}
+
+/// v3 adds per-platform artifacts to the inputs.
+fn v2_to_v3(state: &mut Map<String, Value>) -> Result<()> {
+    if let Some(inputs) = state.get_mut("inputs").and_then(Value::as_object_mut) {
+        inputs
+            .entry("artifacts")
+            .or_insert_with(|| Value::Array(Vec::new()));
+    }
+
+    Ok(())
+}
+
+/// v4 adds the requirements lock used by `python` formula mode.
+fn v3_to_v4(state: &mut Map<String, Value>) -> Result<()> {
+    if let Some(inputs) = state.get_mut("inputs").and_then(Value::as_object_mut) {
+        inputs.entry("python_lock").or_insert(Value::Null);
+    }
+
+    Ok(())
+}
+
+/// v5 adds the formula source kind; earlier runs always used tarballs.
+fn v4_to_v5(state: &mut Map<String, Value>) -> Result<()> {
+    if let Some(inputs) = state.get_mut("inputs").and_then(Value::as_object_mut) {
+        inputs
+            .entry("source_kind")
+            .or_insert_with(|| Value::from("tarball"));
+        for key in ["source_tag", "head_branch"] {
+            inputs.entry(key).or_insert(Value::Null);
+        }
+    }
+
+    Ok(())
+}
+
+/// v6 adds `depends_on` declarations to the inputs.
+fn v5_to_v6(state: &mut Map<String, Value>) -> Result<()> {
+    if let Some(inputs) = state.get_mut("inputs").and_then(Value::as_object_mut) {
+        inputs
+            .entry("depends_on")
+            .or_insert_with(|| Value::Array(Vec::new()));
+    }
+
+    Ok(())
+}
+
+/// v7 adds the `service do` block and caveats to the inputs.
+fn v6_to_v7(state: &mut Map<String, Value>) -> Result<()> {
+    if let Some(inputs) = state.get_mut("inputs").and_then(Value::as_object_mut) {
+        for key in ["service", "caveats"] {
+            inputs.entry(key).or_insert(Value::Null);
+        }
+    }
+
+    Ok(())
+}
+
+/// v8 adds shell completion and man page options to the inputs.
+fn v7_to_v8(state: &mut Map<String, Value>) -> Result<()> {
+    if let Some(inputs) = state.get_mut("inputs").and_then(Value::as_object_mut) {
+        inputs
+            .entry("completions")
+            .or_insert_with(|| Value::from("none"));
+        inputs.entry("manpage").or_insert(Value::Null);
+    }
+
+    Ok(())
+}
+
+/// v9 adds the test strategy inputs and records the generated test in state.
+fn v8_to_v9(state: &mut Map<String, Value>) -> Result<()> {
+    if let Some(inputs) = state.get_mut("inputs").and_then(Value::as_object_mut) {
+        inputs
+            .entry("test_strategy")
+            .or_insert_with(|| Value::from("cli"));
+        for key in ["test_command", "test_expect"] {
+            inputs.entry(key).or_insert(Value::Null);
+        }
+    }
+    state.entry("formula_test").or_insert(Value::Null);
+
+    Ok(())
+}
+
+/// v10 adds the `--license` input and records the formula's license in state.
+fn v9_to_v10(state: &mut Map<String, Value>) -> Result<()> {
+    if let Some(inputs) = state.get_mut("inputs").and_then(Value::as_object_mut) {
+        inputs.entry("license").or_insert(Value::Null);
+    }
+    state.entry("license").or_insert(Value::Null);
+
+    Ok(())
+}
+
+/// v11 adds the `--python` input for `python` mode.
+fn v10_to_v11(state: &mut Map<String, Value>) -> Result<()> {
+    if let Some(inputs) = state.get_mut("inputs").and_then(Value::as_object_mut) {
+        inputs.entry("python").or_insert(Value::Null);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inputs::{FormulaMode, SourceKind};
+    use crate::state::{State, StateStore, StepStatus};
+    use std::fs;
+
+    /// State files as written by each released schema, oldest first.
+    const FIXTURES: &[(u32, &str)] = &[
+        (1, include_str!("../tests/fixtures/state/v1.json")),
+        (2, include_str!("../tests/fixtures/state/v2.json")),
+        (3, include_str!("../tests/fixtures/state/v3.json")),
+        (4, include_str!("../tests/fixtures/state/v4.json")),
+        (5, include_str!("../tests/fixtures/state/v5.json")),
+        (6, include_str!("../tests/fixtures/state/v6.json")),
+        (7, include_str!("../tests/fixtures/state/v7.json")),
+        (8, include_str!("../tests/fixtures/state/v8.json")),
+        (9, include_str!("../tests/fixtures/state/v9.json")),
+        (10, include_str!("../tests/fixtures/state/v10.json")),
+    ];
+
+    fn fixture(version: u32) -> Value {
+        let (_, data) = FIXTURES
+            .iter()
+            .find(|(found, _)| *found == version)
+            .expect("fixture exists");
+        serde_json::from_str(data).expect("fixture is valid JSON")
+    }
+
+    fn load(version: u32) -> State {
+        let (value, from) = migrate(fixture(version)).expect("fixture migrates");
+        assert_eq!(from, version);
+        assert_eq!(value["schema_version"], Value::from(SCHEMA_VERSION));
+        serde_json::from_value(value).expect("migrated fixture parses")
+    }
+
+    #[test]
+    fn every_schema_has_a_fixture_and_a_migration() {
+        assert_eq!(FIXTURES.len() as u32, SCHEMA_VERSION - 1);
+        assert_eq!(MIGRATIONS.len() as u32, SCHEMA_VERSION - 1);
+        for (version, _) in FIXTURES {
+            assert_eq!(fixture(*version)["schema_version"], Value::from(*version));
+        }
+    }
+
+    #[test]
+    fn v1_gets_defaults_and_keeps_step_errors() {
+        let state = load(1);
+        assert_eq!(state.formula_name, None);
+        assert!(!state.summary_printed);
+        assert_eq!(state.steps[1].status, StepStatus::Failed);
+        assert_eq!(
+            state.steps[1].error.as_deref(),
+            Some("brew tap-new returned non-zero status: Some(1)")
+        );
+        assert!(state.steps.iter().all(|step| step.attempts.is_empty()));
+
+        let inputs = state.inputs.expect("inputs");
+        assert!(inputs.artifacts.is_empty());
+        assert_eq!(inputs.source_kind, SourceKind::Tarball);
+        assert!(inputs.depends_on.is_empty());
+        assert_eq!(inputs.license, None);
+    }
+
+    #[test]
+    fn v2_keeps_attempts() {
+        let state = load(2);
+        assert_eq!(state.steps[1].attempts.len(), 2);
+        assert_eq!(state.steps[2].status, StepStatus::Interrupted);
+    }
+
+    #[test]
+    fn intermediate_schemas_keep_their_inputs() {
+        assert_eq!(load(3).inputs.expect("inputs").artifacts.len(), 2);
+
+        let inputs = load(4).inputs.expect("inputs");
+        assert_eq!(inputs.formula_mode, FormulaMode::Python);
+        assert_eq!(
+            inputs.python_lock.as_deref(),
+            Some("/home/octo/tools/requirements.lock")
+        );
+
+        let inputs = load(5).inputs.expect("inputs");
+        assert_eq!(inputs.source_kind, SourceKind::GitTag);
+        assert_eq!(inputs.source_tag.as_deref(), Some("v1.2.0"));
+
+        let inputs = load(6).inputs.expect("inputs");
+        let stanzas: Vec<String> = inputs.depends_on.iter().map(|dep| dep.stanza()).collect();
+        assert_eq!(
+            stanzas,
+            [
+                "depends_on \"pkgconf\" => :build",
+                "depends_on \"openssl@3\""
+            ]
+        );
+
+        let inputs = load(7).inputs.expect("inputs");
+        assert_eq!(
+            inputs.service.expect("service").run,
+            ["tools", "serve", "--port", "8080"]
+        );
+        assert!(inputs.caveats.is_some());
+
+        let inputs = load(8).inputs.expect("inputs");
+        assert_eq!(inputs.manpage.as_deref(), Some("man/tools.1"));
+    }
+
+    #[test]
+    fn v9_keeps_the_recorded_test() {
+        let state = load(9);
+        let test = state.formula_test.expect("formula test");
+        assert_eq!(test.command.as_deref(), Some("tools --help"));
+        assert_eq!(state.license, None);
+        assert!(state.summary_printed);
+    }
+
+    #[test]
+    fn v10_keeps_the_license_and_takes_the_default_python() {
+        let state = load(10);
+        let inputs = state.inputs.expect("inputs");
+        assert_eq!(inputs.formula_mode, FormulaMode::Python);
+        assert_eq!(inputs.python, None);
+        assert_eq!(
+            inputs.license.map(|license| license.to_string()).as_deref(),
+            Some("Apache-2.0")
+        );
+        assert_eq!(
+            state.license.expect("license").source,
+            "--license".to_string()
+        );
+    }
+
+    #[test]
+    fn missing_schema_version_is_v1() {
+        let mut value = fixture(1);
+        value.as_object_mut().unwrap().remove("schema_version");
+        let (_, from) = migrate(value).expect("migrates");
+        assert_eq!(from, 1);
+    }
+
+    #[test]
+    fn current_schema_is_unchanged() {
+        let (value, _) = migrate(fixture(9)).unwrap();
+        let (again, from) = migrate(value.clone()).unwrap();
+        assert_eq!(from, SCHEMA_VERSION);
+        assert_eq!(again, value);
+    }
+
+    #[test]
+    fn rejects_schema_zero_and_future_schemas() {
+        for version in [0, SCHEMA_VERSION + 1] {
+            let mut value = fixture(9);
+            value["schema_version"] = Value::from(version);
+            assert!(migrate(value).is_err(), "schema {version} was accepted");
+        }
+
+        let mut value = fixture(9);
+        value["schema_version"] = Value::from("9");
+        assert!(migrate(value).is_err());
+    }
+
+    #[test]
+    fn state_store_reads_every_fixture() {
+        let dir = std::env::temp_dir().join(format!("tap-setup-fixtures-{}", uuid::Uuid::new_v4()));
+        let store = StateStore::new("homebrew-tap-setup", Some(dir.clone())).unwrap();
+        for (version, data) in FIXTURES {
+            let run_id = format!("v{version}");
+            let run_dir = dir.join("runs").join(&run_id);
+            fs::create_dir_all(&run_dir).unwrap();
+            fs::write(run_dir.join("state.json"), data).unwrap();
+
+            let state = store.read_state(&run_id).unwrap();
+            assert_eq!(state.schema_version, SCHEMA_VERSION);
+        }
+
+        let run_dir = dir.join("runs").join("future");
+        fs::create_dir_all(&run_dir).unwrap();
+        fs::write(
+            run_dir.join("state.json"),
+            format!("{{\"schema_version\": {}}}", SCHEMA_VERSION + 1),
+        )
+        .unwrap();
+        let err = store.read_state("future").unwrap_err();
+        assert!(format!("{err:#}").contains("upgrade homebrew-tap-setup"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
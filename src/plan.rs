@@ -0,0 +1,217 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PlannedAction {
+    Command {
+        program: String,
+        args: Vec<String>,
+    },
+    WriteFile {
+        path: String,
+        contents: String,
+    },
+    EditFile {
+        path: String,
+        before: String,
+        after: String,
+    },
+    CreateRemote {
+        resource: String,
+        name: String,
+    },
+}
+
+impl PlannedAction {
+    pub fn command<I, S>(program: &str, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self::Command {
+            program: program.to_string(),
+            args: args.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    pub fn write_file(path: impl Into<String>, contents: impl Into<String>) -> Self {
+        Self::WriteFile {
+            path: path.into(),
+            contents: contents.into(),
+        }
+    }
+
+    pub fn edit_file(
+        path: impl Into<String>,
+        before: impl Into<String>,
+        after: impl Into<String>,
+    ) -> Self {
+        Self::EditFile {
+            path: path.into(),
+            before: before.into(),
+            after: after.into(),
+        }
+    }
+
+    pub fn create_remote(resource: impl Into<String>, name: impl Into<String>) -> Self {
+        Self::CreateRemote {
+            resource: resource.into(),
+            name: name.into(),
+        }
+    }
+}
+
+impl fmt::Display for PlannedAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlannedAction::Command { program, args } => {
+                write!(f, "run: {}", shell_quote(program))?;
+                for arg in args {
+                    write!(f, " {}", shell_quote(arg))?;
+                }
+                Ok(())
+            }
+            PlannedAction::WriteFile { path, contents } => {
+                write!(f, "create file: {}", path)?;
+                for line in contents.lines() {
+                    write!(f, "\n      + {}", line)?;
+                }
+                Ok(())
+            }
+            PlannedAction::EditFile {
+                path,
+                before,
+                after,
+            } => {
+                write!(f, "edit file: {}", path)?;
+                for (sign, line) in changed_lines(before, after) {
+                    write!(f, "\n      {} {}", sign, line)?;
+                }
+                Ok(())
+            }
+            PlannedAction::CreateRemote { resource, name } => {
+                write!(f, "create {}: {}", resource, name)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StepPlan {
+    pub step_id: String,
+    pub actions: Vec<PlannedAction>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Plan {
+    pub run_id: String,
+    pub created_at: String,
+    pub steps: Vec<StepPlan>,
+}
+
+impl Plan {
+    pub fn new(run_id: &str, created_at: String) -> Self {
+        Self {
+            run_id: run_id.to_string(),
+            created_at,
+            steps: Vec::new(),
+        }
+    }
+
+    /// Returns the first step in `current` whose actions differ from this saved plan.
+    pub fn first_change<'a>(&self, current: &'a Plan) -> Option<&'a str> {
+        current
+            .steps
+            .iter()
+            .find(|step| !self.steps.contains(step))
+            .map(|step| step.step_id.as_str())
+    }
+
+    pub fn print(&self) {
+        for step in &self.steps {
+            println!("==> {}", step.step_id);
+            if step.actions.is_empty() {
+                println!("    no changes");
+            }
+            for action in &step.actions {
+                println!("    {}", action);
+            }
+        }
+    }
+}
+
+/// The lines removed (`-`) and added (`+`) between two versions of a file, in
+/// file order.
+fn changed_lines<'a>(before: &'a str, after: &'a str) -> Vec<(char, &'a str)> {
+    let old: Vec<&str> = before.lines().collect();
+    let new: Vec<&str> = after.lines().collect();
+
+    // common[i][j]: length of the longest common subsequence of old[i..] and new[j..].
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+            changes.push(('-', old[i]));
+            i += 1;
+        } else {
+            changes.push(('+', new[j]));
+            j += 1;
+        }
+    }
+    changes
+}
+
+/// Quotes `value` for a POSIX shell when it contains anything but safe characters.
+pub fn shell_quote(value: &str) -> String {
+    let safe = !value.is_empty()
+        && value
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || "-_./:=@+,%".contains(ch));
+
+    if safe {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_changed_lines_in_file_order() {
+        let before = "class Foo < Formula\n  url \"a\"\n\n  def install\n  end\nend\n";
+        let after = "class Foo < Formula\n  url \"a\"\n  license \"MIT\"\n\n  depends_on \"zlib\"\n\n  def install\n    bin.install \"foo\"\n  end\nend\n";
+        assert_eq!(
+            changed_lines(before, after),
+            [
+                ('+', "  license \"MIT\""),
+                ('+', "  depends_on \"zlib\""),
+                ('+', ""),
+                ('+', "    bin.install \"foo\""),
+            ]
+        );
+
+        assert_eq!(
+            changed_lines("a\nb\nc\n", "a\nB\nc\n"),
+            [('-', "b"), ('+', "B")]
+        );
+        assert!(changed_lines("same\n", "same\n").is_empty());
+    }
+}
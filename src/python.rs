@@ -0,0 +1,466 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use crate::formula;
+use crate::source_url;
+use crate::upstream::{CurlClient, Endpoints, HttpClient};
+
+/// Homebrew's current default Python, used when neither `--python` nor the
+/// lock names one.
+pub const DEFAULT_PYTHON: &str = "python@3.13";
+
+/// A pinned `name==version` line from a requirements lock.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Requirement {
+    pub name: String,
+    pub version: String,
+    /// `--hash=sha256:...` values, when the lock was generated with hashes.
+    pub hashes: Vec<String>,
+}
+
+/// A `resource` block: the sdist of one dependency.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resource {
+    pub name: String,
+    pub url: String,
+    pub sha256: String,
+}
+
+/// A formula that installs a PyPI package into a virtualenv.
+#[derive(Debug, Clone)]
+pub struct PythonFormula {
+    pub class_name: String,
+    pub homepage: String,
+    pub url: String,
+    pub sha256: String,
+    /// The `python@3.x` formula the virtualenv is built with.
+    pub python: String,
+    pub resources: Vec<Resource>,
+}
+
+impl PythonFormula {
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "class {} < Formula", self.class_name);
+        let _ = writeln!(out, "  include Language::Python::Virtualenv\n");
+        let _ = writeln!(out, "  desc \"TODO: add a short description\"");
+        let _ = writeln!(out, "  homepage \"{}\"", self.homepage);
+        let _ = writeln!(out, "  url \"{}\"", self.url);
+        let _ = writeln!(out, "  sha256 \"{}\"", self.sha256);
+
+        if let Some(block) = formula::livecheck_block(&self.url) {
+            out.push('\n');
+            out.push_str(&block);
+        }
+
+        let _ = writeln!(out, "\n  depends_on \"{}\"", self.python);
+        out.push_str(&render_resources(&self.resources));
+
+        let _ = writeln!(out, "\n  def install");
+        let _ = writeln!(out, "    virtualenv_install_with_resources");
+        let _ = writeln!(out, "  end");
+        let _ = writeln!(out, "\n  test do");
+        let _ = writeln!(out, "    # TODO: add a test");
+        let _ = writeln!(out, "  end");
+        out.push_str("end\n");
+        out
+    }
+}
+
+/// PEP 503 name normalization: lowercase, runs of `-_.` become `-`.
+pub fn normalize_name(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    let mut separator = false;
+    for ch in name.trim().chars() {
+        if matches!(ch, '-' | '_' | '.') {
+            separator = true;
+            continue;
+        }
+        if separator && !out.is_empty() {
+            out.push('-');
+        }
+        separator = false;
+        out.push(ch.to_ascii_lowercase());
+    }
+    out
+}
+
+/// Normalizes `--python` to a `python@3.x` formula name; a bare `3.12` is
+/// accepted too.
+pub fn normalize_python(value: &str) -> Result<String> {
+    let trimmed = value.trim();
+    let version = trimmed.strip_prefix("python@").unwrap_or(trimmed);
+    let valid = version.strip_prefix("3.").is_some_and(|minor| {
+        !minor.is_empty()
+            && minor.bytes().all(|byte| byte.is_ascii_digit())
+            && !(minor.len() > 1 && minor.starts_with('0'))
+    });
+    if !valid {
+        bail!(
+            "invalid --python '{}': expected a Homebrew Python formula such as python@3.12",
+            trimmed
+        );
+    }
+    Ok(format!("python@{version}"))
+}
+
+/// The Python a lock was compiled for, from its header: `pip-compile` writes
+/// `... autogenerated by pip-compile with Python 3.12`, and `uv pip compile`
+/// echoes its command line, which may carry `--python-version 3.12`.
+pub fn lock_python(text: &str) -> Option<String> {
+    text.lines()
+        .take_while(|line| line.trim_start().starts_with('#') || line.trim().is_empty())
+        .find_map(|line| {
+            let (_, rest) = line
+                .split_once("with Python ")
+                .or_else(|| line.split_once("--python-version "))
+                .or_else(|| line.split_once("--python-version="))?;
+            let version = rest.split_whitespace().next()?;
+            let mut parts = version.split('.');
+            let (major, minor) = (parts.next()?, parts.next()?);
+            normalize_python(&format!("{major}.{minor}")).ok()
+        })
+}
+
+/// The Python formula for a virtualenv formula: `--python`, else the one the
+/// lock was compiled for, else [`DEFAULT_PYTHON`].
+pub fn python_formula(python: Option<&str>, lock: Option<&Path>) -> Result<String> {
+    if let Some(python) = python {
+        return Ok(python.to_string());
+    }
+    if let Some(lock) = lock {
+        let text = fs::read_to_string(lock)
+            .with_context(|| format!("failed to read lock file: {}", lock.display()))?;
+        if let Some(python) = lock_python(&text) {
+            return Ok(python);
+        }
+    }
+    Ok(DEFAULT_PYTHON.to_string())
+}
+
+/// Reads a requirements-style lock (`pip-compile`, `uv pip compile`,
+/// `uv export`, `poetry export`). Every requirement must be pinned with `==`;
+/// entries only for Windows are dropped.
+pub fn read_lock(path: &Path) -> Result<Vec<Requirement>> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("failed to read lock file: {}", path.display()))?;
+    parse_lock(&text).with_context(|| format!("invalid lock file: {}", path.display()))
+}
+
+pub fn parse_lock(text: &str) -> Result<Vec<Requirement>> {
+    let joined = text.replace("\\\r\n", " ").replace("\\\n", " ");
+    let mut requirements: Vec<Requirement> = Vec::new();
+
+    for line in joined.lines() {
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let mut words = line.split_whitespace();
+        let Some(spec) = words.next() else {
+            continue;
+        };
+        if spec.starts_with('-') {
+            // -e, -r, --index-url, ...: not a requirement.
+            continue;
+        }
+
+        let hashes: Vec<String> = words
+            .filter_map(|word| word.strip_prefix("--hash="))
+            .filter_map(|hash| hash.strip_prefix("sha256:"))
+            .map(str::to_ascii_lowercase)
+            .collect();
+
+        let (spec, marker) = match line.split_once(';') {
+            Some((spec, marker)) => (spec.trim(), marker),
+            None => (spec, ""),
+        };
+        let marker = marker.split(" --hash").next().unwrap_or("");
+        if selects_windows(marker) {
+            continue;
+        }
+
+        let spec = spec.split_whitespace().next().unwrap_or(spec);
+        let Some((name, version)) = spec.split_once("==") else {
+            bail!(
+                "'{}' is not pinned with ==; use a lock file, not a list of ranges",
+                spec
+            );
+        };
+        let name = name.split('[').next().unwrap_or(name);
+        let version = version.trim();
+        if name.is_empty() || version.is_empty() {
+            bail!("could not read requirement '{}'", spec);
+        }
+
+        let name = normalize_name(name);
+        if requirements.iter().any(|found| found.name == name) {
+            bail!("{} is pinned more than once", name);
+        }
+        requirements.push(Requirement {
+            name,
+            version: version.to_string(),
+            hashes,
+        });
+    }
+
+    requirements.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(requirements)
+}
+
+/// Whether an environment marker limits a requirement to Windows. A marker
+/// that excludes Windows (`sys_platform != "win32"`) is what macOS and Linux
+/// install, so it is kept.
+fn selects_windows(marker: &str) -> bool {
+    let marker: String = marker
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '"' && *c != '\'')
+        .collect();
+    marker.contains("sys_platform==win32") || marker.contains("platform_system==Windows")
+}
+
+fn strip_comment(line: &str) -> &str {
+    if line.trim_start().starts_with('#') {
+        return "";
+    }
+    match line.find(" #") {
+        Some(index) => &line[..index],
+        None => line,
+    }
+}
+
+#[derive(Deserialize)]
+struct PypiRelease {
+    urls: Vec<PypiFile>,
+}
+
+#[derive(Deserialize)]
+struct PypiFile {
+    packagetype: String,
+    url: String,
+    digests: PypiDigests,
+}
+
+#[derive(Deserialize)]
+struct PypiDigests {
+    sha256: String,
+}
+
+/// Looks up the sdist of each pinned requirement on PyPI. When the lock has
+/// hashes, the sdist's sha256 must be one of them.
+pub fn resolve(
+    http: &dyn HttpClient,
+    endpoints: &Endpoints,
+    requirements: &[Requirement],
+) -> Result<Vec<Resource>> {
+    requirements
+        .iter()
+        .map(|requirement| {
+            let body = http.get(&format!(
+                "{}/pypi/{}/{}/json",
+                endpoints.pypi, requirement.name, requirement.version
+            ))?;
+            let release: PypiRelease = serde_json::from_str(&body).with_context(|| {
+                format!(
+                    "failed to parse PyPI response for {}=={}",
+                    requirement.name, requirement.version
+                )
+            })?;
+            let Some(sdist) = release
+                .urls
+                .into_iter()
+                .find(|file| file.packagetype == "sdist")
+            else {
+                bail!(
+                    "{}=={} has no source distribution on PyPI; Homebrew resources need an sdist",
+                    requirement.name,
+                    requirement.version
+                );
+            };
+
+            let sha256 = sdist.digests.sha256.to_ascii_lowercase();
+            if !requirement.hashes.is_empty() && !requirement.hashes.contains(&sha256) {
+                bail!(
+                    "sha256 of the {}=={} sdist on PyPI ({}) is not one of the hashes in the lock",
+                    requirement.name,
+                    requirement.version,
+                    sha256
+                );
+            }
+
+            Ok(Resource {
+                name: requirement.name.clone(),
+                url: sdist.url,
+                sha256,
+            })
+        })
+        .collect()
+}
+
+/// Drops the formula's own package from a lock that includes it.
+pub fn without_package(requirements: Vec<Requirement>, url: &str) -> Vec<Requirement> {
+    let own = source_url::analyze(url)
+        .name
+        .map(|name| normalize_name(&name));
+    requirements
+        .into_iter()
+        .filter(|requirement| Some(&requirement.name) != own.as_ref())
+        .collect()
+}
+
+/// Rewrites the resource blocks of an existing formula from `lock`, for use
+/// when bumping its version.
+pub fn update_resources(formula_path: &Path, lock: &Path) -> Result<()> {
+    let source = fs::read_to_string(formula_path)
+        .with_context(|| format!("failed to read formula: {}", formula_path.display()))?;
+    let url = formula::parse(&source)
+        .url
+        .context("formula has no stable url")?;
+
+    let requirements = without_package(read_lock(lock)?, &url);
+    let resources = resolve(&CurlClient, &Endpoints::from_env(), &requirements)?;
+    let updated = replace_resources(&source, &resources)?;
+
+    if updated == source {
+        println!("Resources in {} are up to date", formula_path.display());
+        return Ok(());
+    }
+
+    fs::write(formula_path, updated)
+        .with_context(|| format!("failed to write formula: {}", formula_path.display()))?;
+    println!(
+        "Wrote {} resource blocks to {}",
+        resources.len(),
+        formula_path.display()
+    );
+    Ok(())
+}
+
+/// Renders one `resource` block per entry, each starting with a blank line.
+pub fn render_resources(resources: &[Resource]) -> String {
+    let mut out = String::new();
+    for resource in resources {
+        let _ = writeln!(out, "\n  resource \"{}\" do", resource.name);
+        let _ = writeln!(out, "    url \"{}\"", resource.url);
+        let _ = writeln!(out, "    sha256 \"{}\"", resource.sha256);
+        let _ = writeln!(out, "  end");
+    }
+    out
+}
+
+/// Replaces a formula's top-level `resource` blocks with `resources`, keeping
+/// their position (or putting them before `def install` when there are none).
+pub fn replace_resources(source: &str, resources: &[Resource]) -> Result<String> {
+    let mut kept: Vec<&str> = Vec::new();
+    let mut insert_at: Option<usize> = None;
+    let mut in_resource = false;
+
+    for line in source.lines() {
+        if in_resource {
+            if line == "  end" {
+                in_resource = false;
+            }
+            continue;
+        }
+        if line.starts_with("  resource \"") && line.ends_with(" do") {
+            in_resource = true;
+            insert_at.get_or_insert(kept.len());
+            // Drop the blank line that separated this block from the previous one.
+            if kept.last().is_some_and(|last| last.trim().is_empty()) {
+                kept.pop();
+                insert_at = insert_at.map(|index| index.min(kept.len()));
+            }
+            continue;
+        }
+        kept.push(line);
+    }
+    if in_resource {
+        bail!("unterminated resource block");
+    }
+
+    let insert_at = match insert_at {
+        Some(index) => index,
+        None => kept
+            .iter()
+            .position(|line| *line == "  def install")
+            .map(|index| match index.checked_sub(1) {
+                Some(before) if kept[before].trim().is_empty() => before,
+                _ => index,
+            })
+            .context("formula has no `def install` to put resources before")?,
+    };
+
+    let mut out = String::new();
+    for line in &kept[..insert_at] {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.push_str(&render_resources(resources));
+    for line in &kept[insert_at..] {
+        out.push_str(line);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_python_formulae() {
+        assert_eq!(normalize_python("python@3.12").unwrap(), "python@3.12");
+        assert_eq!(normalize_python(" 3.11 ").unwrap(), "python@3.11");
+        for invalid in [
+            "python",
+            "python@2.7",
+            "python@3",
+            "python@3.x",
+            "3.012",
+            "pypy3",
+        ] {
+            assert!(normalize_python(invalid).is_err(), "{invalid} was accepted");
+        }
+    }
+
+    #[test]
+    fn reads_the_python_from_lock_headers() {
+        let pip_compile = "#\n# This file is autogenerated by pip-compile with Python 3.12\n# by the following command:\n#\n#    pip-compile\n#\nclick==8.1.7\n";
+        assert_eq!(lock_python(pip_compile).as_deref(), Some("python@3.12"));
+
+        let uv = "# This file was autogenerated by uv via the following command:\n#    uv pip compile requirements.in --python-version 3.11.4 -o requirements.lock\nclick==8.1.7\n";
+        assert_eq!(lock_python(uv).as_deref(), Some("python@3.11"));
+
+        let plain = "click==8.1.7\n# compiled with Python 3.10\n";
+        assert_eq!(lock_python(plain), None);
+    }
+
+    #[test]
+    fn skips_only_requirements_for_windows() {
+        let lock = "\
+colorama==0.4.6 ; sys_platform == \"win32\"
+pywin32==306 ; platform_system=='Windows'
+uvloop==0.19.0 ; sys_platform != \"win32\" \\
+    --hash=sha256:ABC
+pexpect==4.9.0 ; platform_system != \"Windows\"
+click==8.1.7
+";
+        let requirements = parse_lock(lock).unwrap();
+        let names: Vec<&str> = requirements.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["click", "pexpect", "uvloop"]);
+        assert_eq!(requirements[2].hashes, ["abc"]);
+    }
+
+    #[test]
+    fn explicit_python_wins_over_the_lock() {
+        assert_eq!(
+            python_formula(Some("python@3.10"), None).unwrap(),
+            "python@3.10"
+        );
+        assert_eq!(python_formula(None, None).unwrap(), DEFAULT_PYTHON);
+    }
+}
@@ -1,6 +1,10 @@
 use anyhow::{Context, Result};
 
-use crate::state::{now_rfc3339, RunContext, StepStatus};
+use crate::exec::{self, RetryPolicy};
+use crate::inputs::InputField;
+use crate::interrupt;
+use crate::plan::{Plan, PlannedAction, StepPlan};
+use crate::state::{now_rfc3339, RunContext, StateKey, StepStatus};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum VerifyStatus {
@@ -12,34 +16,320 @@ pub trait Step {
     fn id(&self) -> &'static str;
     fn description(&self) -> &'static str;
 
+    fn depends_on(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    fn produces(&self) -> &'static [StateKey] {
+        &[]
+    }
+
+    fn consumes(&self) -> &'static [StateKey] {
+        &[]
+    }
+
+    fn reads_inputs(&self) -> &'static [InputField] {
+        &[]
+    }
+
+    /// Policy for retrying the whole apply phase on transient errors.
+    fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy::none()
+    }
+
     fn preflight(&self, ctx: &mut RunContext) -> Result<()>;
     fn apply(&self, ctx: &mut RunContext) -> Result<()>;
     fn verify(&self, ctx: &mut RunContext) -> Result<VerifyStatus>;
+    fn plan(&self, ctx: &mut RunContext) -> Result<Vec<PlannedAction>>;
 
+    #[allow(dead_code)]
     fn undo(&self, _ctx: &mut RunContext) -> Result<()> {
         Ok(())
     }
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct StepSelection {
+    pub only: Vec<String>,
+    pub skip: Vec<String>,
+    pub from: Option<String>,
+    pub until: Option<String>,
+    pub strict: bool,
+}
+
 pub struct Runner {
     steps: Vec<Box<dyn Step>>,
+    selected: Vec<bool>,
+    strict: bool,
 }
 
 impl Runner {
-    pub fn new(steps: Vec<Box<dyn Step>>) -> Self {
-        Self { steps }
+    pub fn new(steps: Vec<Box<dyn Step>>) -> Result<Self> {
+        let steps = order_steps(steps)?;
+        check_state_keys(&steps)?;
+
+        let selected = vec![true; steps.len()];
+        Ok(Self {
+            steps,
+            selected,
+            strict: false,
+        })
+    }
+
+    pub fn select(&mut self, selection: &StepSelection) -> Result<()> {
+        let ids: Vec<&str> = self.steps.iter().map(|step| step.id()).collect();
+        let position = |id: &str| -> Result<usize> {
+            ids.iter().position(|known| *known == id).ok_or_else(|| {
+                anyhow::anyhow!("unknown step '{}'; valid steps: {}", id, ids.join(", "))
+            })
+        };
+
+        for id in selection.only.iter().chain(&selection.skip) {
+            position(id)?;
+        }
+
+        let first = match selection.from.as_deref() {
+            Some(id) => position(id)?,
+            None => 0,
+        };
+        let last = match selection.until.as_deref() {
+            Some(id) => position(id)?,
+            None => ids.len().saturating_sub(1),
+        };
+
+        if first > last {
+            anyhow::bail!("--from {} comes after --until {}", ids[first], ids[last]);
+        }
+
+        self.selected = ids
+            .iter()
+            .enumerate()
+            .map(|(index, id)| {
+                (first..=last).contains(&index)
+                    && (selection.only.is_empty() || selection.only.iter().any(|only| only == id))
+                    && !selection.skip.iter().any(|skip| skip == id)
+            })
+            .collect();
+        self.strict = selection.strict;
+
+        Ok(())
+    }
+
+    fn check_prerequisites(&self, ctx: &RunContext) -> Result<()> {
+        let mut missing: Vec<(&str, Vec<&str>)> = Vec::new();
+
+        for (index, step) in self.steps.iter().enumerate() {
+            if !self.selected[index] {
+                continue;
+            }
+
+            for prereq in step.depends_on() {
+                if self.is_selected(prereq)
+                    || ctx.state.step_status(prereq) == Some(StepStatus::Complete)
+                {
+                    continue;
+                }
+
+                match missing.iter_mut().find(|(id, _)| id == prereq) {
+                    Some((_, dependents)) => dependents.push(step.id()),
+                    None => missing.push((prereq, vec![step.id()])),
+                }
+            }
+        }
+
+        let messages: Vec<String> = missing
+            .iter()
+            .map(|(prereq, dependents)| {
+                format!(
+                    "step {} has never completed (required by {})",
+                    prereq,
+                    dependents.join(", ")
+                )
+            })
+            .collect();
+
+        if messages.is_empty() {
+            return Ok(());
+        }
+
+        if self.strict {
+            anyhow::bail!("{}", messages.join("\n"));
+        }
+
+        for message in messages {
+            eprintln!("Warning: {}", message);
+        }
+
+        Ok(())
+    }
+
+    /// Resets steps that read any of `changed` back to Pending and clears the
+    /// state they produced, so they are re-verified with the new inputs.
+    pub fn invalidate(&self, ctx: &mut RunContext, changed: &[InputField]) -> Result<()> {
+        for step in &self.steps {
+            if !step
+                .reads_inputs()
+                .iter()
+                .any(|field| changed.contains(field))
+            {
+                continue;
+            }
+
+            let Some(index) = ctx
+                .state
+                .steps
+                .iter()
+                .position(|record| record.id == step.id())
+            else {
+                continue;
+            };
+
+            let record = &mut ctx.state.steps[index];
+            if record.status == StepStatus::Pending {
+                continue;
+            }
+
+            record.status = StepStatus::Pending;
+            record.started_at = None;
+            record.finished_at = None;
+            record.error = None;
+            record.skipped_apply = false;
+            record.attempts.clear();
+            for key in step.produces() {
+                ctx.state.clear(*key);
+            }
+            println!("  reset {} to Pending", step.id());
+        }
+
+        ctx.persist()
+    }
+
+    fn is_selected(&self, id: &str) -> bool {
+        self.steps
+            .iter()
+            .position(|step| step.id() == id)
+            .is_some_and(|index| self.selected[index])
+    }
+
+    fn producer_of(&self, key: StateKey) -> Option<&dyn Step> {
+        self.steps
+            .iter()
+            .find(|step| step.produces().contains(&key))
+            .map(|step| step.as_ref())
+    }
+
+    fn check_consumed(&self, step: &dyn Step, ctx: &RunContext) -> Result<()> {
+        for key in step.consumes() {
+            if ctx.state.has(*key) {
+                continue;
+            }
+
+            let producer = self
+                .producer_of(*key)
+                .map(|producer| producer.id())
+                .unwrap_or("<none>");
+            let status = ctx
+                .state
+                .step_status(producer)
+                .map(|status| format!("{status:?}"))
+                .unwrap_or_else(|| "never run".to_string());
+            anyhow::bail!(
+                "step {} needs {} from step {} (status: {}); run {} first",
+                step.id(),
+                key.name(),
+                producer,
+                status,
+                producer
+            );
+        }
+
+        Ok(())
+    }
+
+    pub fn plan(&self, ctx: &mut RunContext) -> Result<Plan> {
+        let mut plan = Plan::new(&ctx.run_id, now_rfc3339());
+
+        for (index, step) in self.steps.iter().enumerate() {
+            let step_id = step.id();
+            if !self.selected[index] || ctx.state.step_status(step_id) == Some(StepStatus::Complete)
+            {
+                continue;
+            }
+
+            self.check_consumed(step.as_ref(), ctx)?;
+            let actions = step
+                .plan(ctx)
+                .with_context(|| format!("Plan failed for step {step_id}"))?;
+            plan.steps.push(StepPlan {
+                step_id: step_id.to_string(),
+                actions,
+            });
+        }
+
+        Ok(plan)
+    }
+
+    fn check_saved_plan(&self, ctx: &mut RunContext) -> Result<()> {
+        let Some(saved) = ctx.state_store.read_plan(&ctx.run_id)? else {
+            return Ok(());
+        };
+
+        let current = self.plan(ctx)?;
+        if let Some(step_id) = saved.first_change(&current) {
+            anyhow::bail!(
+                "Plan for step {step_id} has changed since it was saved at {}. Run `plan --resume {}` again and review it before applying.",
+                saved.created_at,
+                ctx.run_id
+            );
+        }
+
+        Ok(())
     }
 
     pub fn run(&self, ctx: &mut RunContext) -> Result<()> {
         ctx.state.dry_run = ctx.dry_run;
         ctx.persist()?;
 
-        for step in &self.steps {
+        self.check_prerequisites(ctx)?;
+
+        if !ctx.dry_run {
+            self.check_saved_plan(ctx)?;
+        }
+
+        for (position, step) in self.steps.iter().enumerate() {
             let step_id = step.id();
             let step_name = step.description();
             println!("==> {} ({})", step_name, step_id);
 
+            interrupt::check()?;
+
             let index = ctx.state.ensure_step(step_id);
+            if !self.selected[position] {
+                // Only never-run steps are marked; earlier results, errors
+                // included, are kept for the run that selects them again.
+                let record = &mut ctx.state.steps[index];
+                if record.status == StepStatus::Pending {
+                    record.status = StepStatus::Skipped;
+                    record.finished_at = Some(now_rfc3339());
+                    ctx.persist()?;
+                }
+                println!("    skipped (not selected)");
+                continue;
+            }
+
+            let previous = &ctx.state.steps[index];
+            match previous.status {
+                StepStatus::Interrupted => println!(
+                    "    previous run was interrupted here at {}; re-verifying before apply",
+                    previous.finished_at.as_deref().unwrap_or("unknown time")
+                ),
+                StepStatus::Running => println!(
+                    "    previous run stopped here without recording a result (started {}); re-verifying before apply",
+                    previous.started_at.as_deref().unwrap_or("unknown time")
+                ),
+                _ => {}
+            }
+
             {
                 let record = &mut ctx.state.steps[index];
                 record.status = StepStatus::Running;
@@ -47,10 +337,14 @@ impl Runner {
                 record.finished_at = None;
                 record.error = None;
                 record.skipped_apply = false;
+                record.attempts.clear();
             }
+            ctx.current_step = Some(index);
             ctx.persist()?;
 
             let result = (|| -> Result<()> {
+                self.check_consumed(step.as_ref(), ctx)?;
+
                 step.preflight(ctx)
                     .with_context(|| format!("Preflight failed for step {step_id}"))?;
 
@@ -68,6 +362,13 @@ impl Runner {
                 }
 
                 if ctx.dry_run {
+                    let actions = step
+                        .plan(ctx)
+                        .with_context(|| format!("Plan failed for step {step_id}"))?;
+                    for action in &actions {
+                        println!("    would {}", action);
+                    }
+
                     let record = &mut ctx.state.steps[index];
                     record.status = StepStatus::DryRun;
                     record.finished_at = Some(now_rfc3339());
@@ -77,7 +378,7 @@ impl Runner {
                     return Ok(());
                 }
 
-                step.apply(ctx)
+                exec::with_retry(ctx, &step.retry_policy(), step_id, |ctx| step.apply(ctx))
                     .with_context(|| format!("Apply failed for step {step_id}"))?;
 
                 match step
@@ -97,13 +398,25 @@ impl Runner {
                     ),
                 }
             })();
+            ctx.current_step = None;
 
             if let Err(err) = result {
                 let record = &mut ctx.state.steps[index];
-                record.status = StepStatus::Failed;
+                record.status = if interrupt::is_interrupted(&err) {
+                    StepStatus::Interrupted
+                } else {
+                    StepStatus::Failed
+                };
                 record.finished_at = Some(now_rfc3339());
-                record.error = Some(err.to_string());
+                record.error = Some(format!("{err:#}"));
                 ctx.persist()?;
+
+                if ctx.state.steps[index].status == StepStatus::Interrupted {
+                    eprintln!(
+                        "Step {} was interrupted; state saved. Resume with --resume {}",
+                        step_id, ctx.run_id
+                    );
+                }
                 return Err(err);
             }
         }
@@ -111,3 +424,89 @@ impl Runner {
         Ok(())
     }
 }
+
+/// Orders steps so every step runs after its dependencies, keeping registration
+/// order wherever the graph allows it.
+fn order_steps(steps: Vec<Box<dyn Step>>) -> Result<Vec<Box<dyn Step>>> {
+    let ids: Vec<&'static str> = steps.iter().map(|step| step.id()).collect();
+
+    for (index, id) in ids.iter().enumerate() {
+        if ids[..index].contains(id) {
+            anyhow::bail!("step {} is registered more than once", id);
+        }
+    }
+
+    for step in &steps {
+        for dependency in step.depends_on() {
+            if !ids.contains(dependency) {
+                anyhow::bail!("step {} depends on unknown step {}", step.id(), dependency);
+            }
+        }
+    }
+
+    let mut remaining: Vec<Option<Box<dyn Step>>> = steps.into_iter().map(Some).collect();
+    let mut ordered: Vec<Box<dyn Step>> = Vec::with_capacity(remaining.len());
+
+    while ordered.len() < remaining.len() {
+        let next = remaining.iter().position(|slot| {
+            slot.as_ref().is_some_and(|step| {
+                step.depends_on()
+                    .iter()
+                    .all(|dependency| ordered.iter().any(|done| done.id() == *dependency))
+            })
+        });
+
+        match next {
+            Some(index) => {
+                if let Some(step) = remaining[index].take() {
+                    ordered.push(step);
+                }
+            }
+            None => {
+                let blocked: Vec<&str> = remaining.iter().flatten().map(|step| step.id()).collect();
+                anyhow::bail!("dependency cycle among steps: {}", blocked.join(", "));
+            }
+        }
+    }
+
+    Ok(ordered)
+}
+
+/// Ensures every consumed state key is produced by one of the step's
+/// (transitive) dependencies.
+fn check_state_keys(steps: &[Box<dyn Step>]) -> Result<()> {
+    let find = |id: &str| steps.iter().find(|step| step.id() == id);
+
+    for step in steps {
+        for key in step.consumes() {
+            let mut pending: Vec<&str> = step.depends_on().to_vec();
+            let mut seen: Vec<&str> = Vec::new();
+            let mut produced = false;
+
+            while let Some(id) = pending.pop() {
+                if seen.contains(&id) {
+                    continue;
+                }
+                seen.push(id);
+
+                if let Some(dependency) = find(id) {
+                    if dependency.produces().contains(key) {
+                        produced = true;
+                        break;
+                    }
+                    pending.extend_from_slice(dependency.depends_on());
+                }
+            }
+
+            if !produced {
+                anyhow::bail!(
+                    "step {} consumes {} but none of its dependencies produce it",
+                    step.id(),
+                    key.name()
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use anyhow::Result;
+
+use crate::state::{State, StateStore};
+
+pub fn show(store: &StateStore, run_id: &str, at: Option<usize>) -> Result<()> {
+    let history = store.history(run_id)?;
+    let state = match at {
+        Some(index) => {
+            let state = store.read_snapshot(run_id, index)?;
+            println!(
+                "Snapshot {} of {}",
+                index,
+                history.last().copied().unwrap_or(index)
+            );
+            state
+        }
+        None => store.read_state(run_id)?,
+    };
+
+    print_state(&state);
+    println!("State dir: {}", store.base_dir().display());
+
+    if let (None, Some(first), Some(last)) = (at, history.first(), history.last()) {
+        println!(
+            "\nHistory: snapshots {} to {}, one per step status change; view one with `runs show {} --at <n>`",
+            first, last, run_id
+        );
+    }
+
+    Ok(())
+}
+
+fn print_state(state: &State) {
+    println!("Run ID: {}", state.run_id);
+    println!("Started: {}", state.started_at);
+    println!("Dry run: {}", state.dry_run);
+    if let Some(inputs) = &state.inputs {
+        println!("Repo: {}", inputs.repo_slug());
+    }
+    if let Some(tap_path) = &state.tap_path {
+        println!("Tap path: {}", tap_path);
+    }
+    if let Some(test) = &state.formula_test {
+        println!("Formula test: {}", test);
+    }
+    if let Some(detection) = &state.license {
+        println!("License: {} (from {})", detection.license, detection.source);
+    }
+
+    println!("\nSteps");
+    for step in &state.steps {
+        println!("  {:<16} {:?}", step.id, step.status);
+        if let Some(started) = &step.started_at {
+            println!("    started:  {}", started);
+        }
+        if let Some(finished) = &step.finished_at {
+            println!("    finished: {}", finished);
+        }
+
+        for attempt in &step.attempts {
+            match &attempt.error {
+                Some(error) => println!(
+                    "    attempt {} of {} failed at {}: {}",
+                    attempt.attempt, attempt.command, attempt.finished_at, error
+                ),
+                None => println!(
+                    "    attempt {} of {} succeeded at {}",
+                    attempt.attempt, attempt.command, attempt.finished_at
+                ),
+            }
+        }
+
+        if let Some(error) = &step.error {
+            println!("    error: {}", error);
+        }
+    }
+}
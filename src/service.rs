@@ -0,0 +1,187 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Component, Path};
+
+/// What `brew services` runs for a formula, rendered as a `service do` block.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Service {
+    /// An installed binary followed by its arguments.
+    pub run: Vec<String>,
+    pub keep_alive: bool,
+    /// Log file relative to `var`, e.g. `log/mytool.log`.
+    pub log_path: Option<String>,
+}
+
+impl Service {
+    /// Validates `--service-run "<binary> [args...]"` and its options.
+    pub fn new(run: &str, keep_alive: bool, log_path: Option<String>) -> Result<Self> {
+        let run: Vec<String> = run.split_whitespace().map(str::to_string).collect();
+        let Some(binary) = run.first() else {
+            bail!("--service-run is empty");
+        };
+        if binary.contains('/') {
+            bail!(
+                "--service-run must start with the name of an installed binary, not a path ('{}')",
+                binary
+            );
+        }
+        if let Some(word) = run.iter().find(|word| word.contains(['"', '\\', '#'])) {
+            bail!(
+                "--service-run argument '{}' contains a quote, backslash or #",
+                word
+            );
+        }
+
+        let log_path = log_path
+            .map(|path| path.trim().to_string())
+            .filter(|path| !path.is_empty());
+        if let Some(path) = &log_path {
+            let relative = Path::new(path)
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)));
+            if !relative || path.contains(['"', '\\', '#']) {
+                bail!(
+                    "--service-log-path must be a plain path relative to var (e.g. log/mytool.log), got '{}'",
+                    path
+                );
+            }
+        }
+
+        Ok(Self {
+            run,
+            keep_alive,
+            log_path,
+        })
+    }
+
+    pub fn binary(&self) -> &str {
+        &self.run[0]
+    }
+
+    /// The `service do` block, indented for the class body.
+    pub fn render(&self) -> String {
+        let binary = format!("opt_bin/\"{}\"", self.binary());
+        let run = if self.run.len() == 1 {
+            binary
+        } else {
+            let args: Vec<String> = self.run[1..]
+                .iter()
+                .map(|arg| format!("\"{arg}\""))
+                .collect();
+            format!("[{}, {}]", binary, args.join(", "))
+        };
+
+        let mut out = format!("  service do\n    run {run}\n");
+        if self.keep_alive {
+            out.push_str("    keep_alive true\n");
+        }
+        if let Some(path) = &self.log_path {
+            out.push_str(&format!("    log_path var/\"{path}\"\n"));
+            out.push_str(&format!("    error_log_path var/\"{path}\"\n"));
+        }
+        out.push_str("  end\n");
+        out
+    }
+}
+
+impl fmt::Display for Service {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "run '{}'", self.run.join(" "))?;
+        if self.keep_alive {
+            write!(f, ", keep-alive")?;
+        }
+        if let Some(path) = &self.log_path {
+            write!(f, ", log {path}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Trims `--caveats` text; empty text means no caveats.
+pub fn normalize_caveats(text: String) -> Result<Option<String>> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    if text.lines().any(|line| line.trim() == "EOS") {
+        bail!("--caveats cannot contain a line that is just EOS");
+    }
+    Ok(Some(text.to_string()))
+}
+
+/// The `def caveats` method, returning `text` as a squiggly heredoc.
+pub fn render_caveats(text: &str) -> String {
+    let mut out = String::from("  def caveats\n    <<~EOS\n");
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            out.push('\n');
+        } else {
+            out.push_str(&format!("      {}\n", line.trim_end()));
+        }
+    }
+    out.push_str("    EOS\n  end\n");
+    out
+}
+
+pub fn has_service(source: &str) -> bool {
+    source.lines().any(|line| line == "  service do")
+}
+
+pub fn has_caveats(source: &str) -> bool {
+    source.lines().any(|line| line == "  def caveats")
+}
+
+/// Adds whichever of the `def caveats` and `service do` blocks the formula
+/// lacks, after the install methods and before `test do`, where Homebrew's
+/// component order puts them. Existing blocks are left alone.
+pub fn set_blocks(source: &str, service: Option<&Service>, caveats: Option<&str>) -> String {
+    let mut blocks: Vec<String> = Vec::new();
+    if let Some(text) = caveats.filter(|_| !has_caveats(source)) {
+        blocks.push(render_caveats(text));
+    }
+    if let Some(service) = service.filter(|_| !has_service(source)) {
+        blocks.push(service.render());
+    }
+    if blocks.is_empty() {
+        return source.to_string();
+    }
+
+    let lines: Vec<&str> = source.lines().collect();
+    let mut out = String::new();
+    match lines.iter().position(|line| *line == "  test do") {
+        Some(index) => {
+            for line in &lines[..index] {
+                out.push_str(line);
+                out.push('\n');
+            }
+            for block in &blocks {
+                out.push_str(block);
+                out.push('\n');
+            }
+            for line in &lines[index..] {
+                out.push_str(line);
+                out.push('\n');
+            }
+        }
+        None => {
+            let index = lines
+                .iter()
+                .rposition(|line| *line == "end")
+                .unwrap_or(lines.len());
+            for line in &lines[..index] {
+                out.push_str(line);
+                out.push('\n');
+            }
+            for block in &blocks {
+                out.push('\n');
+                out.push_str(block);
+            }
+            for line in &lines[index..] {
+                out.push_str(line);
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
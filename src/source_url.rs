@@ -0,0 +1,441 @@
+use std::fmt;
+
+const ARCHIVE_EXTENSIONS: &[&str] = &[
+    ".tar.gz",
+    ".tar.bz2",
+    ".tar.xz",
+    ".tar.zst",
+    ".tar.lz",
+    ".tar.lzma",
+    ".tar",
+    ".tgz",
+    ".tbz",
+    ".tbz2",
+    ".txz",
+    ".tzst",
+    ".zip",
+    ".7z",
+    ".gem",
+    ".crate",
+    ".whl",
+];
+
+const PRERELEASE_MARKERS: &[&str] = &["rc", "alpha", "beta", "pre", "dev", "post"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hosting {
+    GitHubArchive,
+    GitHubRelease,
+    GitLab,
+    PyPi,
+    Npm,
+    CratesIo,
+    SourceForge,
+    Generic,
+}
+
+impl fmt::Display for Hosting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Hosting::GitHubArchive => "GitHub tag archive",
+            Hosting::GitHubRelease => "GitHub release asset",
+            Hosting::GitLab => "GitLab archive",
+            Hosting::PyPi => "PyPI sdist",
+            Hosting::Npm => "npm tarball",
+            Hosting::CratesIo => "crates.io crate",
+            Hosting::SourceForge => "SourceForge download",
+            Hosting::Generic => "generic archive",
+        };
+        f.write_str(label)
+    }
+}
+
+/// What can be learned about a source archive from its URL alone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceInfo {
+    pub hosting: Hosting,
+    pub name: Option<String>,
+    pub version: Option<String>,
+    /// `owner/repo` (or GitLab `group/.../project`) for forge-hosted sources.
+    pub repository: Option<String>,
+}
+
+impl SourceInfo {
+    /// The name as a Homebrew formula name: lowercase, with '_' as '-'.
+    pub fn formula_name(&self) -> Option<String> {
+        self.name
+            .as_ref()
+            .map(|name| name.to_ascii_lowercase().replace('_', "-"))
+    }
+}
+
+pub fn analyze(url: &str) -> SourceInfo {
+    let url = url.split(['?', '#']).next().unwrap_or(url);
+    let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let (host, path) = without_scheme
+        .split_once('/')
+        .unwrap_or((without_scheme, ""));
+    let host = host.to_ascii_lowercase();
+    let segments: Vec<&str> = path.split('/').filter(|part| !part.is_empty()).collect();
+
+    let hosted = match host.as_str() {
+        "github.com" | "www.github.com" => github(&segments),
+        "codeload.github.com" => codeload(&segments),
+        "gitlab.com" => gitlab(&segments),
+        "files.pythonhosted.org" | "pypi.io" | "pypi.org" => {
+            with_filename(Hosting::PyPi, &segments)
+        }
+        "registry.npmjs.org" => npm(&segments),
+        "crates.io" | "static.crates.io" => crates_io(&segments),
+        "downloads.sourceforge.net" | "sourceforge.net" => sourceforge(&segments),
+        _ => None,
+    };
+
+    hosted.unwrap_or_else(|| {
+        with_filename(Hosting::Generic, &segments).unwrap_or(SourceInfo {
+            hosting: Hosting::Generic,
+            name: None,
+            version: None,
+            repository: None,
+        })
+    })
+}
+
+/// `github.com/<owner>/<repo>/archive/[refs/tags/]<tag>.<ext>`,
+/// `github.com/<owner>/<repo>/releases/download/<tag>/<asset>` and the
+/// repository URL, with or without `.git`.
+fn github(segments: &[&str]) -> Option<SourceInfo> {
+    match segments {
+        [owner, repo] => {
+            let repo = repo.trim_end_matches(".git");
+            Some(SourceInfo {
+                hosting: Hosting::GitHubArchive,
+                name: Some(repo.to_string()),
+                version: None,
+                repository: Some(format!("{owner}/{repo}")),
+            })
+        }
+        [owner, repo, "archive", "refs", "tags", tag] | [owner, repo, "archive", tag] => {
+            Some(SourceInfo {
+                hosting: Hosting::GitHubArchive,
+                name: Some(repo.to_string()),
+                version: version_from_tag(strip_archive_extension(tag), repo),
+                repository: Some(format!("{owner}/{repo}")),
+            })
+        }
+        [owner, repo, "releases", "download", tag, ..] => Some(SourceInfo {
+            hosting: Hosting::GitHubRelease,
+            name: Some(repo.to_string()),
+            version: version_from_tag(tag, repo),
+            repository: Some(format!("{owner}/{repo}")),
+        }),
+        _ => None,
+    }
+}
+
+/// `codeload.github.com/<owner>/<repo>/tar.gz/[refs/tags/]<tag>`.
+fn codeload(segments: &[&str]) -> Option<SourceInfo> {
+    let (owner, repo, tag) = match segments {
+        [owner, repo, _, "refs", "tags", tag] | [owner, repo, _, tag] => (owner, repo, tag),
+        _ => return None,
+    };
+
+    Some(SourceInfo {
+        hosting: Hosting::GitHubArchive,
+        name: Some(repo.to_string()),
+        version: version_from_tag(tag, repo),
+        repository: Some(format!("{owner}/{repo}")),
+    })
+}
+
+/// `gitlab.com/<group>/.../<project>/-/archive/<tag>/<file>`.
+fn gitlab(segments: &[&str]) -> Option<SourceInfo> {
+    let marker = segments.iter().position(|part| *part == "-")?;
+    if segments.get(marker + 1) != Some(&"archive") || marker == 0 {
+        return None;
+    }
+
+    let project = segments[marker - 1];
+    Some(SourceInfo {
+        hosting: Hosting::GitLab,
+        name: Some(project.to_string()),
+        version: segments
+            .get(marker + 2)
+            .and_then(|tag| version_from_tag(tag, project)),
+        repository: Some(segments[..marker].join("/")),
+    })
+}
+
+/// `registry.npmjs.org/[@scope/]<pkg>/-/<pkg>-<version>.tgz`.
+fn npm(segments: &[&str]) -> Option<SourceInfo> {
+    let marker = segments.iter().position(|part| *part == "-")?;
+    let package = *segments.get(marker.checked_sub(1)?)?;
+    let file = strip_archive_extension(segments.get(marker + 1)?);
+    let version = file
+        .strip_prefix(package)
+        .and_then(|rest| rest.strip_prefix('-'))
+        .map(str::to_string);
+
+    Some(SourceInfo {
+        hosting: Hosting::Npm,
+        name: Some(package.to_string()),
+        version,
+        repository: None,
+    })
+}
+
+/// `crates.io/api/v1/crates/<name>/<version>/download` and
+/// `static.crates.io/crates/<name>/<name>-<version>.crate`.
+fn crates_io(segments: &[&str]) -> Option<SourceInfo> {
+    match segments {
+        ["api", "v1", "crates", name, version, "download"] => Some(SourceInfo {
+            hosting: Hosting::CratesIo,
+            name: Some(name.to_string()),
+            version: Some(version.to_string()),
+            repository: None,
+        }),
+        ["crates", name, file] => Some(SourceInfo {
+            hosting: Hosting::CratesIo,
+            name: Some(name.to_string()),
+            version: strip_archive_extension(file)
+                .strip_prefix(name)
+                .and_then(|rest| rest.strip_prefix('-'))
+                .map(str::to_string),
+            repository: None,
+        }),
+        _ => None,
+    }
+}
+
+/// `downloads.sourceforge.net/project/<project>/.../<file>` and
+/// `sourceforge.net/projects/<project>/files/.../<file>/download`.
+fn sourceforge(segments: &[&str]) -> Option<SourceInfo> {
+    let (project, rest) = match segments {
+        ["project", project, rest @ ..] | ["projects", project, "files", rest @ ..] => {
+            (project, rest)
+        }
+        _ => return None,
+    };
+    let rest = rest.strip_suffix(&["download"]).unwrap_or(rest);
+    let version = rest
+        .last()
+        .and_then(|file| split_name_version(strip_archive_extension(file)).1);
+
+    Some(SourceInfo {
+        hosting: Hosting::SourceForge,
+        name: Some(project.to_string()),
+        version,
+        repository: None,
+    })
+}
+
+fn with_filename(hosting: Hosting, segments: &[&str]) -> Option<SourceInfo> {
+    let file = strip_archive_extension(segments.last()?);
+    let (name, version) = split_name_version(file);
+
+    Some(SourceInfo {
+        hosting,
+        name: (!name.is_empty()).then(|| name.to_string()),
+        version,
+        repository: None,
+    })
+}
+
+fn strip_archive_extension(file: &str) -> &str {
+    ARCHIVE_EXTENSIONS
+        .iter()
+        .find_map(|ext| file.strip_suffix(ext))
+        .unwrap_or(file)
+}
+
+/// Splits `tool_1.0_darwin_arm64` into (`tool`, `1.0`) at the first '-' or '_'
+/// followed by a version; anything after the version is dropped.
+pub fn split_name_version(file: &str) -> (&str, Option<String>) {
+    for (index, ch) in file.char_indices() {
+        if ch != '-' && ch != '_' {
+            continue;
+        }
+        if let Some(version) = leading_version(&file[index + 1..]) {
+            return (&file[..index], Some(version));
+        }
+    }
+
+    (file, None)
+}
+
+/// Tags like `v1.2.3`, `1.2.3`, `release-1.2` or `<repo>-1.2`.
+pub fn version_from_tag(tag: &str, repo: &str) -> Option<String> {
+    let tag = tag
+        .strip_prefix(repo)
+        .map(|rest| rest.trim_start_matches(['-', '_']))
+        .unwrap_or(tag);
+    let tag = tag
+        .strip_prefix("release-")
+        .or_else(|| tag.strip_prefix("release_"))
+        .unwrap_or(tag);
+
+    leading_version(tag).or_else(|| split_name_version(tag).1)
+}
+
+/// Reads a version such as `1.2.3`, `v2.0-rc1` or `20240101` from the start
+/// of `text`, without the leading `v`.
+fn leading_version(text: &str) -> Option<String> {
+    let text = text
+        .strip_prefix(['v', 'V'])
+        .filter(|rest| rest.starts_with(|ch: char| ch.is_ascii_digit()))
+        .unwrap_or(text);
+    if !text.starts_with(|ch: char| ch.is_ascii_digit()) {
+        return None;
+    }
+
+    let end = text
+        .find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '.'))
+        .unwrap_or(text.len());
+    let mut version = text[..end].trim_end_matches('.').to_string();
+
+    // Keep pre-release suffixes written with a separator, e.g. `1.0-rc1`.
+    let rest = &text[end..];
+    if let Some(suffix) = rest.strip_prefix(['-', '_']) {
+        let lower = suffix.to_ascii_lowercase();
+        if PRERELEASE_MARKERS
+            .iter()
+            .any(|marker| lower.starts_with(marker))
+        {
+            let suffix_end = suffix
+                .find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '.'))
+                .unwrap_or(suffix.len());
+            version.push('-');
+            version.push_str(&suffix[..suffix_end]);
+        }
+    }
+
+    Some(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn analyzes_the_url_shapes_it_knows() {
+        let cases = [
+            (
+                "https://github.com/acme/tool.git",
+                Hosting::GitHubArchive,
+                Some("tool"),
+                None,
+                Some("acme/tool"),
+            ),
+            (
+                "https://github.com/acme/tool",
+                Hosting::GitHubArchive,
+                Some("tool"),
+                None,
+                Some("acme/tool"),
+            ),
+            (
+                "https://github.com/acme/tool/",
+                Hosting::GitHubArchive,
+                Some("tool"),
+                None,
+                Some("acme/tool"),
+            ),
+            (
+                "https://github.com/acme/tool/archive/refs/tags/v1.2.3.tar.gz",
+                Hosting::GitHubArchive,
+                Some("tool"),
+                Some("1.2.3"),
+                Some("acme/tool"),
+            ),
+            (
+                "https://github.com/acme/tool/archive/tool-1.2.tar.gz",
+                Hosting::GitHubArchive,
+                Some("tool"),
+                Some("1.2"),
+                Some("acme/tool"),
+            ),
+            (
+                "https://github.com/acme/tool/releases/download/v2.0.0/tool_2.0.0_darwin_arm64.tar.gz",
+                Hosting::GitHubRelease,
+                Some("tool"),
+                Some("2.0.0"),
+                Some("acme/tool"),
+            ),
+            (
+                "https://codeload.github.com/acme/tool/tar.gz/refs/tags/v1.0",
+                Hosting::GitHubArchive,
+                Some("tool"),
+                Some("1.0"),
+                Some("acme/tool"),
+            ),
+            (
+                "https://gitlab.com/group/sub/tool/-/archive/v1.4/tool-v1.4.tar.gz",
+                Hosting::GitLab,
+                Some("tool"),
+                Some("1.4"),
+                Some("group/sub/tool"),
+            ),
+            (
+                "https://files.pythonhosted.org/packages/ab/cd/tool_py-0.9.1.tar.gz",
+                Hosting::PyPi,
+                Some("tool_py"),
+                Some("0.9.1"),
+                None,
+            ),
+            (
+                "https://registry.npmjs.org/@acme/tool/-/tool-3.1.0.tgz",
+                Hosting::Npm,
+                Some("tool"),
+                Some("3.1.0"),
+                None,
+            ),
+            (
+                "https://crates.io/api/v1/crates/tool/0.5.0/download",
+                Hosting::CratesIo,
+                Some("tool"),
+                Some("0.5.0"),
+                None,
+            ),
+            (
+                "https://static.crates.io/crates/tool/tool-0.5.0.crate",
+                Hosting::CratesIo,
+                Some("tool"),
+                Some("0.5.0"),
+                None,
+            ),
+            (
+                "https://downloads.sourceforge.net/project/tool/1.0/tool-1.0.tar.gz",
+                Hosting::SourceForge,
+                Some("tool"),
+                Some("1.0"),
+                None,
+            ),
+            (
+                "https://sourceforge.net/projects/tool/files/tool-1.0.tar.gz/download",
+                Hosting::SourceForge,
+                Some("tool"),
+                Some("1.0"),
+                None,
+            ),
+            (
+                "https://example.org/dist/tool-4.2.tar.xz?mirror=1",
+                Hosting::Generic,
+                Some("tool"),
+                Some("4.2"),
+                None,
+            ),
+            ("https://example.org/", Hosting::Generic, None, None, None),
+        ];
+        for (url, hosting, name, version, repository) in cases {
+            let info = analyze(url);
+            assert_eq!(info.hosting, hosting, "hosting of {url}");
+            assert_eq!(info.name.as_deref(), name, "name of {url}");
+            assert_eq!(info.version.as_deref(), version, "version of {url}");
+            assert_eq!(
+                info.repository.as_deref(),
+                repository,
+                "repository of {url}"
+            );
+        }
+    }
+}
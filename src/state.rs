@@ -1,30 +1,50 @@
 use anyhow::{Context, Result};
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
-use std::fs;
+use std::fs::{self, File};
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use time::format_description::well_known::Rfc3339;
 use time::OffsetDateTime;
 use uuid::Uuid;
 
-use crate::inputs::Inputs;
+use crate::binary_formula::Downloads;
+use crate::formula_test::FormulaTest;
+use crate::inputs::{InputField, InputOverrides, Inputs};
+use crate::license::Detection;
+use crate::lock::{self, LockGuard};
+use crate::migrations;
+use crate::plan::Plan;
 
-const APP_NAME: &str = "homebrew-tap-setup";
-const SCHEMA_VERSION: u32 = 1;
+pub const APP_NAME: &str = "homebrew-tap-setup";
+pub const STATE_DIR_ENV: &str = "HOMEBREW_TAP_SETUP_STATE_DIR";
+pub const SCHEMA_VERSION: u32 = 11;
+/// History snapshots kept per run; older ones are pruned.
+pub const MAX_SNAPSHOTS: usize = 200;
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct RunContext {
     pub run_id: String,
     pub dry_run: bool,
     pub state_store: StateStore,
     pub state: State,
     pub inputs: Inputs,
+    pub current_step: Option<usize>,
+    /// Step statuses as of the newest history snapshot.
+    snapshot_steps: Vec<(String, StepStatus)>,
+    _locks: Vec<LockGuard>,
 }
 
 impl RunContext {
-    pub fn new(dry_run: bool, inputs: Inputs) -> Result<Self> {
+    pub fn new(
+        state_store: StateStore,
+        dry_run: bool,
+        inputs: Inputs,
+        force_unlock: bool,
+    ) -> Result<Self> {
         let run_id = Uuid::new_v4().to_string();
-        let state_store = StateStore::new(APP_NAME)?;
+        let tap_lock = state_store.lock_tap(&inputs, &run_id, force_unlock)?;
+        let run_lock = state_store.lock_run(&run_id, force_unlock)?;
         let mut state = State::new(run_id.clone());
         state.dry_run = dry_run;
         state.inputs = Some(inputs.clone());
@@ -35,18 +55,34 @@ impl RunContext {
             run_id,
             dry_run,
             state_store,
+            snapshot_steps: state.step_statuses(),
             state,
             inputs,
+            current_step: None,
+            _locks: vec![run_lock, tap_lock],
         })
     }
 
-    pub fn load(run_id: String, dry_run: bool) -> Result<Self> {
-        let state_store = StateStore::new(APP_NAME)?;
+    pub fn load(
+        state_store: StateStore,
+        run_id: String,
+        dry_run: bool,
+        force_unlock: bool,
+    ) -> Result<Self> {
+        if !state_store.run_exists(&run_id)? {
+            anyhow::bail!(
+                "run {} not found: {}",
+                run_id,
+                state_store.state_path(&run_id).display()
+            );
+        }
+        let run_lock = state_store.lock_run(&run_id, force_unlock)?;
         let mut state = state_store.read_state(&run_id)?;
         let inputs = state
             .inputs
             .clone()
             .ok_or_else(|| anyhow::anyhow!("state does not contain inputs"))?;
+        let tap_lock = state_store.lock_tap(&inputs, &run_id, force_unlock)?;
 
         state.dry_run = dry_run;
         state_store.write_state(&run_id, &state)?;
@@ -55,13 +91,84 @@ impl RunContext {
             run_id,
             dry_run,
             state_store,
+            snapshot_steps: state.step_statuses(),
             state,
             inputs,
+            current_step: None,
+            _locks: vec![run_lock, tap_lock],
         })
     }
 
-    pub fn persist(&self) -> Result<()> {
-        self.state_store.write_state(&self.run_id, &self.state)
+    pub fn downloads(&self) -> Downloads {
+        Downloads::new(self.state_store.downloads_dir(&self.run_id))
+    }
+
+    /// Writes the state, and a history snapshot when a step changed status.
+    pub fn persist(&mut self) -> Result<()> {
+        self.state_store.write_state(&self.run_id, &self.state)?;
+
+        let steps = self.state.step_statuses();
+        if steps != self.snapshot_steps {
+            self.state_store.write_snapshot(&self.run_id, &self.state)?;
+            self.snapshot_steps = steps;
+        }
+        Ok(())
+    }
+
+    /// Replaces stored inputs with resume-time overrides and returns the fields
+    /// that actually changed.
+    pub fn apply_overrides(
+        &mut self,
+        overrides: InputOverrides,
+        force_unlock: bool,
+    ) -> Result<Vec<InputField>> {
+        let updated = self.inputs.with_overrides(overrides)?;
+        let changed = self.inputs.changed_fields(&updated);
+        if changed.is_empty() {
+            return Ok(changed);
+        }
+
+        if updated.repo_slug() != self.inputs.repo_slug() {
+            let tap_lock = self
+                .state_store
+                .lock_tap(&updated, &self.run_id, force_unlock)?;
+            self._locks.push(tap_lock);
+        }
+
+        println!("Input changes since the run was created:");
+        for field in &changed {
+            println!(
+                "  {}: {} -> {}",
+                field.name(),
+                self.inputs.field_value(*field),
+                updated.field_value(*field)
+            );
+        }
+
+        self.state.inputs = Some(updated.clone());
+        self.inputs = updated;
+        self.persist()?;
+
+        Ok(changed)
+    }
+
+    pub fn record_attempt(
+        &mut self,
+        command: &str,
+        attempt: u32,
+        error: Option<String>,
+    ) -> Result<()> {
+        let Some(index) = self.current_step else {
+            return Ok(());
+        };
+
+        self.state.steps[index].attempts.push(AttemptRecord {
+            command: command.to_string(),
+            attempt,
+            finished_at: now_rfc3339(),
+            error,
+        });
+        self.persist()
     }
 }
 
@@ -72,13 +179,13 @@ pub struct State {
     pub started_at: String,
     pub steps: Vec<StepRecord>,
     pub dry_run: bool,
-    #[serde(default)]
     pub inputs: Option<Inputs>,
-    #[serde(default)]
     pub tap_path: Option<String>,
-    #[serde(default)]
     pub formula_name: Option<String>,
-    #[serde(default)]
+    /// The `test do` the formula was generated with.
+    pub formula_test: Option<FormulaTest>,
+    /// The formula's license and how it was found.
+    pub license: Option<Detection>,
     pub summary_printed: bool,
 }
 
@@ -93,6 +200,8 @@ impl State {
             inputs: None,
             tap_path: None,
             formula_name: None,
+            formula_test: None,
+            license: None,
             summary_printed: false,
         }
     }
@@ -105,6 +214,71 @@ impl State {
             self.steps.len() - 1
         }
     }
+
+    pub fn has(&self, key: StateKey) -> bool {
+        match key {
+            StateKey::TapPath => self
+                .tap_path
+                .as_deref()
+                .is_some_and(|path| !path.trim().is_empty()),
+            StateKey::FormulaName => self.formula_name.is_some(),
+            StateKey::FormulaTest => self.formula_test.is_some(),
+            StateKey::License => self.license.is_some(),
+            StateKey::SummaryPrinted => self.summary_printed,
+        }
+    }
+
+    pub fn clear(&mut self, key: StateKey) {
+        match key {
+            StateKey::TapPath => self.tap_path = None,
+            StateKey::FormulaName => self.formula_name = None,
+            StateKey::FormulaTest => self.formula_test = None,
+            StateKey::License => self.license = None,
+            StateKey::SummaryPrinted => self.summary_printed = false,
+        }
+    }
+
+    pub fn tap_path(&self) -> Result<&str> {
+        self.tap_path
+            .as_deref()
+            .filter(|path| !path.trim().is_empty())
+            .ok_or_else(|| anyhow::anyhow!("tap path is not set in run state"))
+    }
+
+    fn step_statuses(&self) -> Vec<(String, StepStatus)> {
+        self.steps
+            .iter()
+            .map(|step| (step.id.clone(), step.status))
+            .collect()
+    }
+
+    pub fn step_status(&self, id: &str) -> Option<StepStatus> {
+        self.steps
+            .iter()
+            .find(|step| step.id == id)
+            .map(|step| step.status)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateKey {
+    TapPath,
+    FormulaName,
+    FormulaTest,
+    License,
+    SummaryPrinted,
+}
+
+impl StateKey {
+    pub fn name(self) -> &'static str {
+        match self {
+            StateKey::TapPath => "tap_path",
+            StateKey::FormulaName => "formula_name",
+            StateKey::FormulaTest => "formula_test",
+            StateKey::License => "license",
+            StateKey::SummaryPrinted => "summary_printed",
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -115,6 +289,7 @@ pub struct StepRecord {
     pub finished_at: Option<String>,
     pub error: Option<String>,
     pub skipped_apply: bool,
+    pub attempts: Vec<AttemptRecord>,
 }
 
 impl StepRecord {
@@ -126,10 +301,19 @@ impl StepRecord {
             finished_at: None,
             error: None,
             skipped_apply: false,
+            attempts: Vec::new(),
         }
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AttemptRecord {
+    pub command: String,
+    pub attempt: u32,
+    pub finished_at: String,
+    pub error: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 pub enum StepStatus {
     Pending,
@@ -137,6 +321,8 @@ pub enum StepStatus {
     Complete,
     Failed,
     DryRun,
+    Skipped,
+    Interrupted,
 }
 
 #[derive(Debug, Clone)]
@@ -145,37 +331,279 @@ pub struct StateStore {
 }
 
 impl StateStore {
-    pub fn new(app_name: &str) -> Result<Self> {
+    /// Resolves the state directory: an explicit `--state-dir` (or
+    /// `HOMEBREW_TAP_SETUP_STATE_DIR`) wins; otherwise the platform state
+    /// directory is used (`$XDG_STATE_HOME` on Linux), and runs left in the
+    /// old config directory are moved there.
+    pub fn new(app_name: &str, state_dir: Option<PathBuf>) -> Result<Self> {
+        if let Some(base_dir) = state_dir {
+            return Ok(Self { base_dir });
+        }
+
         let project_dirs =
-            ProjectDirs::from("", "", app_name).context("Could not resolve config directory")?;
-        let base_dir = project_dirs.config_dir().to_path_buf();
+            ProjectDirs::from("", "", app_name).context("Could not resolve state directory")?;
+        let base_dir = project_dirs
+            .state_dir()
+            .unwrap_or_else(|| project_dirs.data_local_dir())
+            .to_path_buf();
+        let store = Self { base_dir };
+        store.migrate_legacy_runs(project_dirs.config_dir())?;
 
-        Ok(Self { base_dir })
+        Ok(store)
+    }
+
+    /// Moves runs from `<legacy_dir>/runs` into this store. Runs that are
+    /// locked or already present in the new location are left in place.
+    fn migrate_legacy_runs(&self, legacy_dir: &Path) -> Result<()> {
+        let legacy_runs = legacy_dir.join("runs");
+        if legacy_dir == self.base_dir || !legacy_runs.is_dir() {
+            return Ok(());
+        }
+
+        let runs_dir = self.base_dir.join("runs");
+        fs::create_dir_all(&runs_dir)
+            .with_context(|| format!("Failed to create runs directory: {}", runs_dir.display()))?;
+
+        let mut moved = 0;
+        for entry in fs::read_dir(&legacy_runs)
+            .with_context(|| format!("Failed to read runs: {}", legacy_runs.display()))?
+        {
+            let source = entry?.path();
+            let Some(run_id) = source.file_name() else {
+                continue;
+            };
+            let target = runs_dir.join(run_id);
+            if !source.is_dir() || target.exists() {
+                continue;
+            }
+            if source.join("lock").exists() {
+                eprintln!(
+                    "Warning: not migrating locked run {}; it stays in {}",
+                    run_id.to_string_lossy(),
+                    legacy_runs.display()
+                );
+                continue;
+            }
+
+            move_dir(&source, &target).with_context(|| {
+                format!(
+                    "Failed to move run {} to {}",
+                    source.display(),
+                    target.display()
+                )
+            })?;
+            moved += 1;
+        }
+
+        if moved > 0 {
+            eprintln!(
+                "Note: moved {} run(s) from {} to {}",
+                moved,
+                legacy_runs.display(),
+                runs_dir.display()
+            );
+        }
+        let _ = fs::remove_dir(&legacy_runs);
+
+        Ok(())
+    }
+
+    /// Whether the run has a state file, or snapshots to recover one from.
+    pub fn run_exists(&self, run_id: &str) -> Result<bool> {
+        Ok(self.state_path_internal(run_id).exists() || !self.history(run_id)?.is_empty())
     }
 
     pub fn read_state(&self, run_id: &str) -> Result<State> {
         let state_path = self.state_path_internal(run_id);
-        let data = fs::read(&state_path)
-            .with_context(|| format!("Failed to read state: {}", state_path.display()))?;
-        let state = serde_json::from_slice(&data)
-            .with_context(|| format!("Failed to parse state: {}", state_path.display()))?;
+        match Self::read_state_file(&state_path) {
+            Ok(state) => Ok(state),
+            Err(err) => self.recover_state(run_id, err),
+        }
+    }
+
+    fn read_state_file(path: &Path) -> Result<State> {
+        let data =
+            fs::read(path).with_context(|| format!("Failed to read state: {}", path.display()))?;
+        let raw: serde_json::Value = serde_json::from_slice(&data)
+            .with_context(|| format!("Failed to parse state: {}", path.display()))?;
+        let (raw, from_version) = migrations::migrate(raw)
+            .with_context(|| format!("Failed to load state: {}", path.display()))?;
+        if from_version < SCHEMA_VERSION {
+            eprintln!(
+                "Note: migrated {} from schema {} to {}",
+                path.display(),
+                from_version,
+                SCHEMA_VERSION
+            );
+        }
+
+        let state = serde_json::from_value(raw)
+            .with_context(|| format!("Failed to parse state: {}", path.display()))?;
         Ok(state)
     }
 
+    /// Falls back to the newest readable history snapshot when `state.json` is
+    /// missing or corrupted, and restores it as the current state.
+    fn recover_state(&self, run_id: &str, err: anyhow::Error) -> Result<State> {
+        for index in self.history(run_id)?.into_iter().rev() {
+            let snapshot = self.snapshot_path(run_id, index);
+            if let Ok(state) = Self::read_state_file(&snapshot) {
+                eprintln!(
+                    "Warning: {:#}; recovered from snapshot {}",
+                    err,
+                    snapshot.display()
+                );
+                let data = serde_json::to_vec_pretty(&state)?;
+                write_atomic(&self.state_path_internal(run_id), &data)?;
+                return Ok(state);
+            }
+        }
+
+        Err(err)
+    }
+
+    pub fn read_snapshot(&self, run_id: &str, index: usize) -> Result<State> {
+        let history = self.history(run_id)?;
+        if !history.contains(&index) {
+            match (history.first(), history.last()) {
+                (Some(first), Some(last)) => anyhow::bail!(
+                    "run {} has history snapshots {} to {}; --at must be in that range",
+                    run_id,
+                    first,
+                    last
+                ),
+                _ => anyhow::bail!("run {} has no history snapshots", run_id),
+            }
+        }
+
+        Self::read_state_file(&self.snapshot_path(run_id, index))
+    }
+
+    /// The numbers of the run's history snapshots, oldest first. Numbers keep
+    /// counting up when old snapshots are pruned.
+    pub fn history(&self, run_id: &str) -> Result<Vec<usize>> {
+        let dir = self.history_dir(run_id);
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut indices = Vec::new();
+        for entry in fs::read_dir(&dir)
+            .with_context(|| format!("Failed to read history: {}", dir.display()))?
+        {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                if let Some(index) = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(|stem| stem.parse().ok())
+                {
+                    indices.push(index);
+                }
+            }
+        }
+
+        indices.sort_unstable();
+        Ok(indices)
+    }
+
     pub fn init_run(&self, run_id: &str, state: &State) -> Result<()> {
         let run_dir = self.run_dir(run_id);
         fs::create_dir_all(&run_dir)
             .with_context(|| format!("Failed to create run directory: {}", run_dir.display()))?;
-        self.write_state(run_id, state)
+        self.write_state(run_id, state)?;
+        self.write_snapshot(run_id, state)
     }
 
     pub fn write_state(&self, run_id: &str, state: &State) -> Result<()> {
         let state_path = self.state_path_internal(run_id);
         let data = serde_json::to_vec_pretty(state)?;
-        fs::write(&state_path, data)
+        write_atomic(&state_path, &data)
             .with_context(|| format!("Failed to write state: {}", state_path.display()))
     }
 
+    /// Adds a history snapshot of `state` and prunes the oldest ones beyond
+    /// `MAX_SNAPSHOTS`.
+    pub fn write_snapshot(&self, run_id: &str, state: &State) -> Result<()> {
+        let history_dir = self.history_dir(run_id);
+        fs::create_dir_all(&history_dir).with_context(|| {
+            format!(
+                "Failed to create history directory: {}",
+                history_dir.display()
+            )
+        })?;
+
+        let history = self.history(run_id)?;
+        let snapshot = self.snapshot_path(run_id, history.last().map_or(1, |last| last + 1));
+        let data = serde_json::to_vec_pretty(state)?;
+        write_atomic(&snapshot, &data)
+            .with_context(|| format!("Failed to write snapshot: {}", snapshot.display()))?;
+
+        let excess = (history.len() + 1).saturating_sub(MAX_SNAPSHOTS);
+        for index in &history[..excess] {
+            let old = self.snapshot_path(run_id, *index);
+            fs::remove_file(&old)
+                .with_context(|| format!("Failed to prune snapshot: {}", old.display()))?;
+        }
+        Ok(())
+    }
+
+    pub fn read_plan(&self, run_id: &str) -> Result<Option<Plan>> {
+        let plan_path = self.plan_path(run_id);
+        if !plan_path.exists() {
+            return Ok(None);
+        }
+
+        let data = fs::read(&plan_path)
+            .with_context(|| format!("Failed to read plan: {}", plan_path.display()))?;
+        let plan = serde_json::from_slice(&data)
+            .with_context(|| format!("Failed to parse plan: {}", plan_path.display()))?;
+        Ok(Some(plan))
+    }
+
+    pub fn write_plan(&self, run_id: &str, plan: &Plan) -> Result<()> {
+        let plan_path = self.plan_path(run_id);
+        let data = serde_json::to_vec_pretty(plan)?;
+        write_atomic(&plan_path, &data)
+            .with_context(|| format!("Failed to write plan: {}", plan_path.display()))
+    }
+
+    pub fn plan_path(&self, run_id: &str) -> PathBuf {
+        self.run_dir(run_id).join("plan.json")
+    }
+
+    pub fn lock_run(&self, run_id: &str, force_unlock: bool) -> Result<LockGuard> {
+        lock::acquire(
+            &self.run_dir(run_id).join("lock"),
+            &format!("run {run_id}"),
+            run_id,
+            force_unlock,
+        )
+    }
+
+    pub fn lock_tap(&self, inputs: &Inputs, run_id: &str, force_unlock: bool) -> Result<LockGuard> {
+        let key = format!("{}--{}.lock", inputs.owner, inputs.repo_name);
+        lock::acquire(
+            &self.base_dir.join("locks").join(key),
+            &format!("tap {}", inputs.repo_slug()),
+            run_id,
+            force_unlock,
+        )
+    }
+
+    /// Archives the run has downloaded, keyed by URL.
+    pub fn downloads_dir(&self, run_id: &str) -> PathBuf {
+        self.run_dir(run_id).join("downloads")
+    }
+
+    fn history_dir(&self, run_id: &str) -> PathBuf {
+        self.run_dir(run_id).join("history")
+    }
+
+    fn snapshot_path(&self, run_id: &str, index: usize) -> PathBuf {
+        self.history_dir(run_id).join(format!("{index:06}.json"))
+    }
+
     fn run_dir(&self, run_id: &str) -> PathBuf {
         self.base_dir.join("runs").join(run_id)
     }
@@ -188,12 +616,75 @@ impl StateStore {
         self.state_path_internal(run_id)
     }
 
-    #[allow(dead_code)]
     pub fn base_dir(&self) -> &Path {
         &self.base_dir
     }
 }
 
+/// Renames `source` to `target`, falling back to copy-and-delete when they are
+/// on different filesystems.
+fn move_dir(source: &Path, target: &Path) -> Result<()> {
+    if fs::rename(source, target).is_ok() {
+        return Ok(());
+    }
+
+    copy_dir(source, target)?;
+    fs::remove_dir_all(source)?;
+    Ok(())
+}
+
+fn copy_dir(source: &Path, target: &Path) -> Result<()> {
+    fs::create_dir_all(target)?;
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let path = entry.path();
+        let dest = target.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&path, &dest)?;
+        } else {
+            fs::copy(&path, &dest)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes through a temp file, fsyncs it, and renames it over `path` so readers
+/// never observe a partially written file.
+pub fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    let dir = path
+        .parent()
+        .ok_or_else(|| anyhow::anyhow!("path has no parent: {}", path.display()))?;
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let tmp_path = dir.join(format!(".{}.tmp-{}", file_name, std::process::id()));
+
+    {
+        let mut file = File::create(&tmp_path)
+            .with_context(|| format!("Failed to create {}", tmp_path.display()))?;
+        file.write_all(data)
+            .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+        file.sync_all()
+            .with_context(|| format!("Failed to sync {}", tmp_path.display()))?;
+    }
+
+    fs::rename(&tmp_path, path).with_context(|| {
+        format!(
+            "Failed to rename {} to {}",
+            tmp_path.display(),
+            path.display()
+        )
+    })?;
+
+    // Persist the rename itself; not every platform supports syncing a directory.
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+
+    Ok(())
+}
+
 pub fn now_rfc3339() -> String {
     let now = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
     now.format(&Rfc3339)
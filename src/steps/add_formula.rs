@@ -3,56 +3,285 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
-use crate::inputs::FormulaMode;
+use crate::binary_formula::{self, BinaryFormula, PlatformAsset};
+use crate::completions;
+use crate::dependency;
+use crate::exec::{self, CommandExt};
+use crate::forge::{Forge, GitHub};
+use crate::formula;
+use crate::formula_test::{self, FormulaTest};
+use crate::inputs::{Completions, FormulaMode, InputField, Platform, SourceKind, TestStrategy};
+use crate::license::{self, Confidence, Detection};
+use crate::plan::PlannedAction;
+use crate::python::{self, PythonFormula};
 use crate::runner::{Step, VerifyStatus};
-use crate::state::RunContext;
+use crate::service::{self, Service};
+use crate::source_url::{self, Hosting};
+use crate::state::{RunContext, StateKey};
+use crate::upstream::{self, CurlClient, Endpoints};
+use crate::validate;
 
 pub struct AddFormulaStep;
 
+/// A formula's contents after [`AddFormulaStep::merge`].
+struct Merged {
+    contents: String,
+    /// What merging changed, e.g. "added livecheck block to <path>".
+    changes: Vec<String>,
+    /// The license the formula declares after merging.
+    license: Option<Detection>,
+    /// The test the inputs ask for.
+    test: FormulaTest,
+}
+
+/// A formula file and the contents apply gives it.
+struct Rendered {
+    formula_name: String,
+    path: PathBuf,
+    /// The file as it is now; `None` when apply creates it.
+    current: Option<String>,
+    merged: Merged,
+}
+
+/// The source stanzas of a stub formula.
+enum StubSource {
+    /// `url`/`sha256` placeholders to fill in by hand.
+    Placeholder,
+    Artifacts(Vec<PlatformAsset>),
+    GitTag {
+        url: String,
+        tag: String,
+        revision: String,
+        head_branch: Option<String>,
+    },
+    Head {
+        url: String,
+        branch: String,
+    },
+}
+
 impl AddFormulaStep {
     pub fn new() -> Self {
         Self
     }
 
-    fn tap_path<'a>(ctx: &'a RunContext) -> Result<&'a str> {
-        ctx.state
-            .tap_path
-            .as_deref()
-            .filter(|path| !path.trim().is_empty())
-            .ok_or_else(|| anyhow::anyhow!("tap path is not set; brew tap-new must run first"))
-    }
-
     fn formula_dir(tap_path: &Path) -> PathBuf {
         tap_path.join("Formula")
     }
 
     fn stub_formula_path(ctx: &RunContext) -> Result<PathBuf> {
-        let tap_path = Self::tap_path(ctx)?;
+        let tap_path = ctx.state.tap_path()?;
         let dir = Self::formula_dir(Path::new(tap_path));
         Ok(dir.join(format!("{}.rb", ctx.inputs.tap)))
     }
 
-    fn write_stub(path: &Path, formula_class: &str) -> Result<()> {
-        let content = format!(
-            "class {formula_class} < Formula\n  desc \"TODO: add a short description\"\n  homepage \"https://example.com\"\n  url \"https://example.com/TODO.tar.gz\"\n  sha256 \"TODO\"\n  license \"MIT\"\n\n  def install\n    # TODO: install steps\n  end\n\n  test do\n    # TODO: add a test\n  end\nend\n"
+    /// Resolves what the stub's source stanzas need: artifact checksums or
+    /// the commit a tag points at.
+    fn stub_source(ctx: &RunContext) -> Result<StubSource> {
+        let url = ctx.inputs.formula_url.clone().unwrap_or_default();
+        match ctx.inputs.source_kind {
+            SourceKind::Tarball if ctx.inputs.artifacts.is_empty() => Ok(StubSource::Placeholder),
+            SourceKind::Tarball => Ok(StubSource::Artifacts(binary_formula::resolve_artifacts(
+                &ctx.inputs.artifacts,
+                &ctx.downloads(),
+            )?)),
+            SourceKind::GitTag => {
+                let tag = ctx.inputs.source_tag.clone().unwrap_or_default();
+                let revision = upstream::tag_revision(&url, &tag)?;
+                Ok(StubSource::GitTag {
+                    url,
+                    tag,
+                    revision,
+                    head_branch: ctx.inputs.head_branch.clone(),
+                })
+            }
+            SourceKind::Head => Ok(StubSource::Head {
+                url,
+                branch: ctx
+                    .inputs
+                    .head_branch
+                    .clone()
+                    .unwrap_or_else(|| "main".to_string()),
+            }),
+        }
+    }
+
+    fn render_stub(formula_class: &str, source: &StubSource) -> String {
+        if let StubSource::Placeholder = source {
+            return format!(
+                "class {formula_class} < Formula\n  desc \"TODO: add a short description\"\n  homepage \"https://example.com\"\n  url \"{url}\"\n  sha256 \"TODO\"\n\n  def install\n    # TODO: install steps\n  end\n\n  test do\n    # TODO: add a test\n  end\nend\n",
+                url = formula::PLACEHOLDER_URL
+            );
+        }
+
+        let homepage = match source {
+            StubSource::GitTag { url, .. } | StubSource::Head { url, .. } => {
+                let info = source_url::analyze(url);
+                info.repository
+                    .filter(|_| info.hosting == Hosting::GitHubArchive)
+                    .map(|repo| format!("https://github.com/{repo}"))
+            }
+            _ => None,
+        };
+        let mut out = format!(
+            "class {formula_class} < Formula\n  desc \"TODO: add a short description\"\n  homepage \"{}\"\n",
+            homepage.as_deref().unwrap_or("https://example.com")
         );
 
-        fs::write(path, content)
-            .with_context(|| format!("failed to write stub formula: {}", path.display()))
+        match source {
+            StubSource::Placeholder => {}
+            StubSource::Artifacts(assets) => {
+                if let Some(version) = assets
+                    .first()
+                    .and_then(|asset| source_url::analyze(&asset.url).version)
+                {
+                    out.push_str(&format!("  version \"{version}\"\n"));
+                }
+                out.push_str(&binary_formula::render_platforms(assets));
+            }
+            StubSource::GitTag {
+                url,
+                tag,
+                revision,
+                head_branch,
+            } => {
+                out.push_str(&format!(
+                    "  url \"{url}\",\n      tag:      \"{tag}\",\n      revision: \"{revision}\"\n"
+                ));
+                if let Some(branch) = head_branch {
+                    out.push_str(&format!("  head \"{url}\", branch: \"{branch}\"\n"));
+                }
+                out.push('\n');
+                out.push_str(&formula::git_tag_livecheck_block());
+            }
+            StubSource::Head { url, branch } => {
+                out.push_str(&format!("  head \"{url}\", branch: \"{branch}\"\n"));
+            }
+        }
+
+        out.push_str(
+            "\n  def install\n    # TODO: install steps\n  end\n\n  test do\n    # TODO: add a test\n  end\nend\n",
+        );
+        out
     }
 
-    fn formula_class_name(tap: &str) -> String {
-        tap.split(|ch: char| ch == '-' || ch == '_')
-            .filter(|part| !part.is_empty())
-            .map(|part| {
-                let mut chars = part.chars();
-                match chars.next() {
-                    Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
-                    None => String::new(),
+    /// Checks that an existing stub declares the source the inputs ask for.
+    fn check_stub_source(path: &Path, source: &str, ctx: &RunContext) -> Result<()> {
+        let url = ctx.inputs.formula_url.as_deref().unwrap_or("");
+        match ctx.inputs.source_kind {
+            SourceKind::Tarball if ctx.inputs.artifacts.is_empty() => Ok(()),
+            SourceKind::Tarball => {
+                Self::check_platform_checksums(path, source, &Self::artifact_platforms(ctx))
+            }
+            SourceKind::GitTag => {
+                let parsed = formula::parse(source);
+                let tag = ctx.inputs.source_tag.as_deref();
+                if parsed.url.as_deref() != Some(url) || parsed.tag.as_deref() != tag {
+                    anyhow::bail!(
+                        "{} does not build {} at tag {}",
+                        path.display(),
+                        url,
+                        tag.unwrap_or_default()
+                    );
+                }
+                if !parsed.has_revision() {
+                    anyhow::bail!(
+                        "{} has no 40-character revision for tag {}",
+                        path.display(),
+                        tag.unwrap_or_default()
+                    );
                 }
+                Ok(())
+            }
+            SourceKind::Head => {
+                let parsed = formula::parse(source);
+                if parsed.head.as_deref() != Some(url) {
+                    anyhow::bail!("{} has no `head \"{}\"` stanza", path.display(), url);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Parses the formula and fails unless every platform it declares, and
+    /// every `--artifact` platform, has a real sha256.
+    fn check_platform_checksums(path: &Path, source: &str, expected: &[Platform]) -> Result<()> {
+        let parsed = formula::parse(source);
+
+        let mut problems: Vec<String> = parsed
+            .platforms
+            .iter()
+            .filter(|platform| !platform.has_checksum())
+            .map(|platform| {
+                format!(
+                    "{} has sha256 {}",
+                    platform.label(),
+                    platform.sha256.as_deref().unwrap_or("<missing>")
+                )
             })
-            .collect::<Vec<_>>()
-            .join("")
+            .collect();
+
+        for platform in expected {
+            let (os, arch) = binary_formula::platform_parts(*platform);
+            let declared = parsed.platforms.iter().any(|found| {
+                found.os.as_deref() == Some(os.block())
+                    && found.arch.as_deref() == Some(arch.block())
+            });
+            if !declared {
+                problems.push(format!("{}/{} is not declared", os.block(), arch.block()));
+            }
+        }
+
+        if !problems.is_empty() {
+            anyhow::bail!(
+                "{} has incomplete platform checksums: {}",
+                path.display(),
+                problems.join("; ")
+            );
+        }
+        Ok(())
+    }
+
+    fn read_formula(path: &Path) -> Result<String> {
+        fs::read_to_string(path)
+            .with_context(|| format!("failed to read formula: {}", path.display()))
+    }
+
+    fn artifact_platforms(ctx: &RunContext) -> Vec<Platform> {
+        ctx.inputs
+            .artifacts
+            .iter()
+            .map(|artifact| artifact.platform)
+            .collect()
+    }
+
+    fn stub_class_name(ctx: &RunContext) -> Result<String> {
+        let class_name = formula::class_name(&ctx.inputs.tap);
+        if !formula::is_ruby_constant(&class_name) {
+            anyhow::bail!(
+                "formula '{}' would get class name '{}', which is not a valid Ruby constant; use a tap name starting with a letter",
+                ctx.inputs.tap,
+                class_name
+            );
+        }
+        Ok(class_name)
+    }
+
+    fn check_stub_class(path: &Path, source: &str, expected: &str) -> Result<()> {
+        match formula::parse_class_name(source) {
+            Some(found) if found == expected => Ok(()),
+            Some(found) => anyhow::bail!(
+                "{} defines class {}, but Homebrew expects {}",
+                path.display(),
+                found,
+                expected
+            ),
+            None => anyhow::bail!(
+                "{} does not define a Formula class (expected `class {} < Formula`)",
+                path.display(),
+                expected
+            ),
+        }
     }
 
     fn has_formula_files(dir: &Path) -> Result<bool> {
@@ -95,34 +324,611 @@ impl AddFormulaStep {
         Ok(names)
     }
 
-    fn derive_name_from_url(url: &str) -> Option<String> {
-        let url = url.split('?').next().unwrap_or(url);
-        let url = url.split('#').next().unwrap_or(url);
-        let filename = url.rsplit('/').next()?;
+    fn brew_create_name(ctx: &RunContext) -> Result<String> {
+        let url = ctx.inputs.formula_url.as_deref().unwrap_or("");
+        ctx.inputs
+            .formula_name
+            .clone()
+            .or_else(|| source_url::analyze(url).formula_name())
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "formula-name is required when formula-name cannot be derived from URL"
+                )
+            })
+    }
+
+    fn brew_create_args(ctx: &RunContext) -> Result<Vec<String>> {
+        let url = ctx.inputs.formula_url.clone().unwrap_or_default();
+        let mut args = vec![
+            "create".to_string(),
+            "--tap".to_string(),
+            ctx.inputs.repo_slug(),
+            "--set-name".to_string(),
+            Self::brew_create_name(ctx)?,
+        ];
+        if let Some(version) = source_url::analyze(&url).version {
+            args.push("--set-version".to_string());
+            args.push(version);
+        }
+        args.push(url);
+        Ok(args)
+    }
+
+    fn release_formula_name(ctx: &RunContext) -> Result<String> {
+        if let Some(name) = &ctx.inputs.formula_name {
+            return Ok(name.clone());
+        }
+
+        let url = ctx.inputs.formula_url.as_deref().unwrap_or("");
+        let (repo, _) = binary_formula::release_target(url)?;
+        let name = binary_formula::release_formula_name(&repo);
+        validate::formula_name(&name).context("pass --formula-name to choose a valid name")?;
+        Ok(name)
+    }
+
+    fn release_formula_path(ctx: &RunContext) -> Result<PathBuf> {
+        let tap_path = ctx.state.tap_path()?;
+        let dir = Self::formula_dir(Path::new(tap_path));
+        Ok(dir.join(format!("{}.rb", Self::release_formula_name(ctx)?)))
+    }
+
+    /// Lists the release's assets and renders a binary formula for them.
+    fn render_release_formula(ctx: &RunContext) -> Result<String> {
+        let url = ctx.inputs.formula_url.as_deref().unwrap_or("");
+        let (repo, tag) = binary_formula::release_target(url)?;
+        let forge = GitHub;
+        let release = forge.release(&repo, tag.as_deref())?;
+        let checksums = binary_formula::checksums_asset(&release).ok_or_else(|| {
+            anyhow::anyhow!(
+                "release {} of {} has no checksums file (checksums.txt or SHA256SUMS)",
+                release.tag_name,
+                repo
+            )
+        })?;
+        let checksums = forge.asset_text(&repo, &release.tag_name, checksums)?;
+
+        let formula = BinaryFormula::from_release(
+            &release,
+            &repo,
+            &Self::release_formula_name(ctx)?,
+            &checksums,
+        )?;
+        Ok(formula.render())
+    }
+
+    fn python_formula_name(ctx: &RunContext) -> Result<String> {
+        if let Some(name) = &ctx.inputs.formula_name {
+            return Ok(name.clone());
+        }
+
+        let url = ctx.inputs.formula_url.as_deref().unwrap_or("");
+        let name = source_url::analyze(url).formula_name().ok_or_else(|| {
+            anyhow::anyhow!("formula-name is required when it cannot be derived from the URL")
+        })?;
+        validate::formula_name(&name).context("pass --formula-name to choose a valid name")?;
+        Ok(name)
+    }
+
+    fn python_formula_path(ctx: &RunContext) -> Result<PathBuf> {
+        let tap_path = ctx.state.tap_path()?;
+        let dir = Self::formula_dir(Path::new(tap_path));
+        Ok(dir.join(format!("{}.rb", Self::python_formula_name(ctx)?)))
+    }
+
+    /// The `python@3.x` formula the virtualenv depends on.
+    fn python_formula(ctx: &RunContext) -> Result<String> {
+        python::python_formula(
+            ctx.inputs.python.as_deref(),
+            ctx.inputs.python_lock.as_deref().map(Path::new),
+        )
+    }
+
+    /// Resolves the lock's pins to sdists on PyPI and renders a virtualenv
+    /// formula with one resource per dependency.
+    fn render_python_formula(ctx: &RunContext) -> Result<String> {
+        let url = ctx.inputs.formula_url.clone().unwrap_or_default();
+        let source = source_url::analyze(&url);
+        let requirements = match &ctx.inputs.python_lock {
+            Some(lock) => python::without_package(python::read_lock(Path::new(lock))?, &url),
+            None => Vec::new(),
+        };
+        let resources = python::resolve(&CurlClient, &Endpoints::from_env(), &requirements)?;
+
+        let name = Self::python_formula_name(ctx)?;
+        let homepage = match (source.hosting, &source.name) {
+            (Hosting::PyPi, Some(project)) => format!("https://pypi.org/project/{project}/"),
+            _ => "https://example.com".to_string(),
+        };
+        let formula = PythonFormula {
+            class_name: formula::class_name(&name),
+            homepage,
+            sha256: ctx.downloads().sha256(&url)?,
+            url,
+            python: Self::python_formula(ctx)?,
+            resources,
+        };
+        Ok(formula.render())
+    }
+
+    /// The binary completions are generated from: the first one the install
+    /// section puts in `bin`.
+    fn main_binary(source: &str, formula_name: &str) -> String {
+        formula::installed_binaries(source, formula_name)
+            .and_then(|binaries| binaries.into_iter().next())
+            .unwrap_or_else(|| formula_name.to_string())
+    }
+
+    /// The test the inputs ask for, aimed at the formula's binary or library.
+    fn formula_test(ctx: &RunContext, source: &str, formula_name: &str) -> FormulaTest {
+        let target = match ctx.inputs.test_strategy {
+            TestStrategy::Library => formula_test::library_name(formula_name),
+            TestStrategy::Cli | TestStrategy::Command => Self::main_binary(source, formula_name),
+        };
+        FormulaTest {
+            strategy: ctx.inputs.test_strategy,
+            target,
+            command: ctx.inputs.test_command.clone(),
+            expect: ctx.inputs.test_expect.clone(),
+            head_only: ctx.inputs.source_kind == SourceKind::Head,
+        }
+    }
+
+    /// Whether the formula has the livecheck block its URL calls for; apply
+    /// adds a missing one.
+    fn check_livecheck(path: &Path) -> Result<bool> {
+        let source = Self::read_formula(path)?;
+        let missing = formula::add_livecheck(&source).is_some();
+        if missing {
+            println!("    {} has no `livecheck` block", path.display());
+        }
+        Ok(!missing)
+    }
+
+    /// Whether the `test do` block is real; apply replaces a placeholder.
+    fn check_test(path: &Path) -> Result<bool> {
+        let source = fs::read_to_string(path)
+            .with_context(|| format!("failed to read formula: {}", path.display()))?;
+        let placeholder = formula_test::has_placeholder_test(&source);
+        if placeholder {
+            println!(
+                "    {} has no real `test do` block, which `brew audit --new` rejects",
+                path.display()
+            );
+        }
+        Ok(!placeholder)
+    }
+
+    /// The archive to read the license from: the formula's source tarball,
+    /// its first platform artifact, or the GitHub archive of its git tag.
+    fn license_archive_url(source: &str) -> Option<String> {
+        let parsed = formula::parse(source);
+        if let (Some(url), Some(tag)) = (&parsed.url, &parsed.tag) {
+            let info = source_url::analyze(url);
+            return info
+                .repository
+                .filter(|_| info.hosting == Hosting::GitHubArchive)
+                .map(|repo| format!("https://github.com/{repo}/archive/refs/tags/{tag}.tar.gz"));
+        }
+        parsed
+            .url
+            .filter(|url| url != formula::PLACEHOLDER_URL)
+            .or_else(|| {
+                parsed
+                    .platforms
+                    .iter()
+                    .find_map(|platform| platform.url.clone())
+            })
+    }
+
+    /// The `--license` input, or the license detected in the source archive.
+    /// An archive that cannot be read leaves the license undetected.
+    fn resolve_license(ctx: &RunContext, source: &str) -> Option<Detection> {
+        if let Some(license) = &ctx.inputs.license {
+            return Some(Detection::explicit(license.clone()));
+        }
+
+        let url = Self::license_archive_url(source)?;
+        println!("    detecting license in {}", url);
+        match ctx
+            .downloads()
+            .get(&url)
+            .and_then(|archive| license::detect(&archive))
+        {
+            Ok(detection) => detection,
+            Err(err) => {
+                println!("    could not read a license from {}: {:#}", url, err);
+                None
+            }
+        }
+    }
+
+    /// Whether the formula declares the `--license` value; apply replaces
+    /// whatever it declares otherwise.
+    fn check_license(path: &Path, ctx: &RunContext) -> Result<bool> {
+        let Some(expected) = &ctx.inputs.license else {
+            return Ok(true);
+        };
+
+        let source = fs::read_to_string(path)
+            .with_context(|| format!("failed to read formula: {}", path.display()))?;
+        let declared = license::declared(&source).as_ref() == Some(expected);
+        if !declared {
+            println!(
+                "    {} does not declare `{}`",
+                path.display(),
+                expected.stanza()
+            );
+        }
+        Ok(declared)
+    }
+
+    fn wants_completions(ctx: &RunContext) -> bool {
+        ctx.inputs.completions != Completions::None || ctx.inputs.manpage.is_some()
+    }
+
+    /// Whether the completion and man page stanzas are present.
+    fn check_completions(path: &Path, formula_name: &str, ctx: &RunContext) -> Result<bool> {
+        if !Self::wants_completions(ctx) {
+            return Ok(true);
+        }
+
+        let source = fs::read_to_string(path)
+            .with_context(|| format!("failed to read formula: {}", path.display()))?;
+        let binary = Self::main_binary(&source, formula_name);
+        let mut missing = completions::missing(
+            &source,
+            ctx.inputs.completions,
+            ctx.inputs.manpage.as_deref(),
+            &binary,
+        );
+        missing.extend(completions::missing_test(
+            &source,
+            ctx.inputs.completions,
+            &binary,
+        ));
+        if !missing.is_empty() {
+            println!("    {} is missing: {}", path.display(), missing.join(", "));
+        }
+        Ok(missing.is_empty())
+    }
+
+    /// Whether every `--depends-on` line is declared; missing ones are added
+    /// by apply.
+    fn check_dependencies(path: &Path, ctx: &RunContext) -> Result<bool> {
+        let source = fs::read_to_string(path)
+            .with_context(|| format!("failed to read formula: {}", path.display()))?;
+        let declared = dependency::declared(&source);
+        let missing: Vec<String> = ctx
+            .inputs
+            .depends_on
+            .iter()
+            .filter(|dependency| !declared.contains(dependency))
+            .map(|dependency| dependency.stanza())
+            .collect();
+        if !missing.is_empty() {
+            println!("    {} is missing: {}", path.display(), missing.join(", "));
+        }
+        Ok(missing.is_empty())
+    }
+
+    fn check_service_binary(
+        path: &Path,
+        source: &str,
+        formula_name: &str,
+        service: &Service,
+    ) -> Result<()> {
+        let Some(installed) = formula::installed_binaries(source, formula_name) else {
+            return Ok(());
+        };
+        if !installed.iter().any(|binary| binary == service.binary()) {
+            anyhow::bail!(
+                "the service runs {}, but the install section of {} installs only: {}",
+                service.binary(),
+                path.display(),
+                installed.join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    /// Whether the `service do` and `def caveats` blocks are present. A
+    /// service whose binary the formula does not install is an error.
+    fn check_service(path: &Path, formula_name: &str, ctx: &RunContext) -> Result<bool> {
+        if ctx.inputs.service.is_none() && ctx.inputs.caveats.is_none() {
+            return Ok(true);
+        }
 
-        let mut base = filename.to_string();
-        for ext in [".tar.gz", ".tgz", ".tar.bz2", ".tar.xz", ".zip"] {
-            if let Some(stripped) = base.strip_suffix(ext) {
-                base = stripped.to_string();
-                break;
+        let source = fs::read_to_string(path)
+            .with_context(|| format!("failed to read formula: {}", path.display()))?;
+        let mut missing = Vec::new();
+        if let Some(service) = &ctx.inputs.service {
+            Self::check_service_binary(path, &source, formula_name, service)?;
+            if !service::has_service(&source) {
+                missing.push("`service do` block");
             }
         }
+        if ctx.inputs.caveats.is_some() && !service::has_caveats(&source) {
+            missing.push("`def caveats` method");
+        }
+        if !missing.is_empty() {
+            println!("    {} is missing: {}", path.display(), missing.join(", "));
+        }
+        Ok(missing.is_empty())
+    }
+
+    /// The formula an earlier `brew create` left in the tap, so a resumed
+    /// or re-verified run merges into it instead of creating it again.
+    fn created_formula(ctx: &RunContext, formula_dir: &Path) -> Option<(String, PathBuf)> {
+        // A reset step forgets the recorded name; `--set-name` still finds it.
+        [
+            ctx.state.formula_name.clone(),
+            Self::brew_create_name(ctx).ok(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|name| {
+            let path = formula_dir.join(format!("{name}.rb"));
+            (name, path)
+        })
+        .find(|(_, path)| path.exists())
+    }
+
+    /// Runs `brew create` and returns the new formula's name and path.
+    fn brew_create(ctx: &RunContext, formula_dir: &Path) -> Result<(String, PathBuf)> {
+        let formula_name = Self::brew_create_name(ctx)?;
+        let args = Self::brew_create_args(ctx)?;
+        println!("    brew {}", args.join(" "));
+
+        let output = Command::new("brew")
+            .env("HOMEBREW_EDITOR", "/usr/bin/true")
+            .env("EDITOR", "/usr/bin/true")
+            .args(&args)
+            .stream_within(exec::APPLY_TIMEOUT)
+            .context("failed to run brew create")?;
+
+        if !output.status.success() {
+            anyhow::bail!(
+                "brew create returned non-zero status: {:?}: {}",
+                output.status.code(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        let names = Self::collect_formula_names(formula_dir)?;
+        let formula_name = if names.len() == 1 {
+            names[0].clone()
+        } else {
+            formula_name
+        };
+        let formula_path = formula_dir.join(format!("{formula_name}.rb"));
+        Ok((formula_name, formula_path))
+    }
 
-        if let Some((prefix, suffix)) = base.rsplit_once('-') {
-            let looks_like_version = suffix
-                .chars()
-                .next()
-                .map(|ch| ch.is_ascii_digit() || ch == 'v')
-                .unwrap_or(false);
-            if looks_like_version {
-                base = prefix.to_string();
+    /// Checks the inputs that apply merges into an existing formula file.
+    /// Anything missing makes the step incomplete so apply adds it; only
+    /// conflicts apply cannot resolve are errors.
+    fn check_additions(path: &Path, formula_name: &str, ctx: &RunContext) -> Result<VerifyStatus> {
+        let mut complete = Self::check_livecheck(path)?;
+        complete &= Self::check_dependencies(path, ctx)?;
+        complete &= Self::check_service(path, formula_name, ctx)?;
+        complete &= Self::check_completions(path, formula_name, ctx)?;
+        complete &= Self::check_test(path)?;
+        complete &= Self::check_license(path, ctx)?;
+        Ok(if complete {
+            VerifyStatus::Complete
+        } else {
+            VerifyStatus::Incomplete
+        })
+    }
+
+    /// Brings a formula's contents in line with the inputs: the livecheck
+    /// block, `--depends-on`, service and caveats blocks, license, test, and
+    /// completion stanzas. What someone already wrote (a license, a real
+    /// test) is kept, except in `fresh` contents this run just generated.
+    /// Returns the contents and what changed in an existing file, for apply's
+    /// output.
+    fn merge(
+        ctx: &RunContext,
+        path: &Path,
+        formula_name: &str,
+        source: String,
+        fresh: bool,
+    ) -> Result<Merged> {
+        let mut changes = Vec::new();
+        let mut changed = |before: &str, after: String, change: &str| {
+            if after != before {
+                changes.push(format!("{} {}", change, path.display()));
             }
+            after
+        };
+
+        let contents = match formula::add_livecheck(&source) {
+            Some(updated) => changed(&source, updated, "added livecheck block to"),
+            None => source,
+        };
+
+        let updated = dependency::set_dependencies(&contents, &ctx.inputs.depends_on);
+        let contents = changed(&contents, updated, "added dependencies to");
+
+        // The service's binary must be one the install section installs.
+        if let Some(service) = &ctx.inputs.service {
+            Self::check_service_binary(path, &contents, formula_name, service)?;
         }
+        let updated = service::set_blocks(
+            &contents,
+            ctx.inputs.service.as_ref(),
+            ctx.inputs.caveats.as_deref(),
+        );
+        let contents = changed(&contents, updated, "added service/caveats blocks to");
 
-        if base.is_empty() {
-            None
+        // Fill in a missing or placeholder license (any license, given
+        // `--license`); a license someone already chose is kept.
+        let (contents, license) =
+            if ctx.inputs.license.is_none() && !license::has_placeholder(&contents) {
+                let declared = license::declared(&contents).map(|license| Detection {
+                    license,
+                    confidence: Confidence::High,
+                    source: "formula".to_string(),
+                    note: None,
+                });
+                (contents, declared)
+            } else {
+                match Self::resolve_license(ctx, &contents) {
+                    Some(detection) => {
+                        let updated = license::set_license(&contents, &detection.license);
+                        let change = format!("set license {} in", detection.license);
+                        (changed(&contents, updated, &change), Some(detection))
+                    }
+                    None => (contents, None),
+                }
+            };
+
+        // Replace a placeholder `test do` (the stub's TODO, `brew create`'s
+        // `system "false"`); a test someone wrote is kept.
+        let test = Self::formula_test(ctx, &contents, formula_name);
+        let updated = formula_test::set_test(&contents, &test, fresh);
+        let contents = changed(&contents, updated, &format!("added {} test to", test));
+
+        let updated = completions::set_stanzas(
+            &contents,
+            ctx.inputs.completions,
+            ctx.inputs.manpage.as_deref(),
+            &Self::main_binary(&contents, formula_name),
+        );
+        let contents = changed(&contents, updated, "added completion/man page stanzas to");
+
+        Ok(Merged {
+            contents,
+            changes,
+            license,
+            test,
+        })
+    }
+
+    /// The formula file as apply leaves it: the existing file, or `fresh`
+    /// rendered with the inputs, checked for conflicts and merged with the
+    /// inputs. `plan` shows the same result that apply writes.
+    fn render(
+        ctx: &RunContext,
+        formula_name: String,
+        path: PathBuf,
+        fresh: impl FnOnce() -> Result<String>,
+    ) -> Result<Rendered> {
+        let current = if path.exists() {
+            Some(Self::read_formula(&path)?)
         } else {
-            Some(base)
+            None
+        };
+        let source = match &current {
+            Some(source) => source.clone(),
+            None => fresh()?,
+        };
+
+        match ctx.inputs.formula_mode {
+            FormulaMode::Stub => {
+                Self::check_stub_class(&path, &source, &Self::stub_class_name(ctx)?)?;
+                Self::check_stub_source(&path, &source, ctx)
+                    .context("remove the formula to regenerate it from the inputs")?;
+            }
+            FormulaMode::BrewCreate => {}
+            FormulaMode::GithubRelease => {
+                Self::check_stub_class(&path, &source, &formula::class_name(&formula_name))?;
+                Self::check_platform_checksums(&path, &source, &[])?;
+            }
+            FormulaMode::Python => {
+                Self::check_stub_class(&path, &source, &formula::class_name(&formula_name))?;
+            }
+        }
+
+        let merged = Self::merge(ctx, &path, &formula_name, source, current.is_none())?;
+        Ok(Rendered {
+            formula_name,
+            path,
+            current,
+            merged,
+        })
+    }
+
+    /// Renders the formula for the inputs' mode. `None` means `brew create`
+    /// has yet to write a `brew-create` formula.
+    fn render_formula(ctx: &RunContext, formula_dir: &Path) -> Result<Option<Rendered>> {
+        let rendered = match ctx.inputs.formula_mode {
+            FormulaMode::Stub => Self::render(
+                ctx,
+                ctx.inputs.tap.clone(),
+                Self::stub_formula_path(ctx)?,
+                || {
+                    let source = Self::stub_source(ctx)?;
+                    Ok(Self::render_stub(&Self::stub_class_name(ctx)?, &source))
+                },
+            )?,
+            FormulaMode::BrewCreate => match Self::created_formula(ctx, formula_dir) {
+                Some((formula_name, formula_path)) => {
+                    let missing = formula_path.clone();
+                    Self::render(ctx, formula_name, formula_path, || {
+                        anyhow::bail!("{} was removed while reading it", missing.display())
+                    })?
+                }
+                None => return Ok(None),
+            },
+            FormulaMode::GithubRelease => Self::render(
+                ctx,
+                Self::release_formula_name(ctx)?,
+                Self::release_formula_path(ctx)?,
+                || Self::render_release_formula(ctx),
+            )?,
+            FormulaMode::Python => Self::render(
+                ctx,
+                Self::python_formula_name(ctx)?,
+                Self::python_formula_path(ctx)?,
+                || Self::render_python_formula(ctx),
+            )?,
+        };
+        Ok(Some(rendered))
+    }
+
+    /// Writes a rendered formula if it changed and records its name, license
+    /// and test.
+    fn write_rendered(ctx: &mut RunContext, rendered: Rendered) -> Result<()> {
+        let Rendered {
+            formula_name,
+            path,
+            current,
+            merged,
+        } = rendered;
+        if current.as_deref() != Some(merged.contents.as_str()) {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).with_context(|| {
+                    format!("failed to create Formula directory: {}", dir.display())
+                })?;
+            }
+            fs::write(&path, &merged.contents)
+                .with_context(|| format!("failed to write formula: {}", path.display()))?;
+            if current.is_none() {
+                println!("    wrote {}", path.display());
+            } else {
+                for change in &merged.changes {
+                    println!("    {}", change);
+                }
+            }
+        }
+
+        ctx.state.license = merged.license;
+        ctx.state.formula_test = Some(merged.test);
+        Self::set_formula_name(ctx, formula_name)
+    }
+
+    /// What apply would do with a rendered formula.
+    fn planned(rendered: Rendered) -> Vec<PlannedAction> {
+        let path = rendered.path.to_string_lossy();
+        match rendered.current {
+            None => vec![PlannedAction::write_file(path, rendered.merged.contents)],
+            Some(current) if current == rendered.merged.contents => Vec::new(),
+            Some(current) => vec![PlannedAction::edit_file(
+                path,
+                current,
+                rendered.merged.contents,
+            )],
         }
     }
 
@@ -147,110 +953,183 @@ impl Step for AddFormulaStep {
         "Add formula"
     }
 
+    fn depends_on(&self) -> &'static [&'static str] {
+        &["brew_tap_new"]
+    }
+
+    fn reads_inputs(&self) -> &'static [InputField] {
+        &[
+            InputField::Owner,
+            InputField::Tap,
+            InputField::RepoName,
+            InputField::FormulaMode,
+            InputField::FormulaUrl,
+            InputField::FormulaName,
+            InputField::Artifacts,
+            InputField::PythonLock,
+            InputField::Python,
+            InputField::SourceKind,
+            InputField::SourceTag,
+            InputField::HeadBranch,
+            InputField::DependsOn,
+            InputField::Service,
+            InputField::Caveats,
+            InputField::Completions,
+            InputField::Manpage,
+            InputField::TestStrategy,
+            InputField::TestCommand,
+            InputField::TestExpect,
+            InputField::License,
+        ]
+    }
+
+    fn produces(&self) -> &'static [StateKey] {
+        &[
+            StateKey::FormulaName,
+            StateKey::FormulaTest,
+            StateKey::License,
+        ]
+    }
+
+    fn consumes(&self) -> &'static [StateKey] {
+        &[StateKey::TapPath]
+    }
+
     fn preflight(&self, ctx: &mut RunContext) -> Result<()> {
-        let tap_path = Self::tap_path(ctx)?;
+        let tap_path = ctx.state.tap_path()?;
         let path = Path::new(tap_path);
 
         if !path.exists() {
             anyhow::bail!("tap path does not exist: {}", path.display());
         }
 
-        if ctx.inputs.formula_mode == FormulaMode::BrewCreate
+        if ctx.inputs.formula_mode != FormulaMode::Stub
             && ctx.inputs.formula_url.as_deref().unwrap_or("").is_empty()
         {
-            anyhow::bail!("formula-url is required for brew-create mode");
+            anyhow::bail!(
+                "formula-url is required for brew-create, github-release and python modes"
+            );
+        }
+
+        if ctx.inputs.formula_mode == FormulaMode::GithubRelease {
+            let name = Self::release_formula_name(ctx)?;
+            let class_name = formula::class_name(&name);
+            if !formula::is_ruby_constant(&class_name) {
+                anyhow::bail!(
+                    "formula '{}' would get class name '{}', which is not a valid Ruby constant",
+                    name,
+                    class_name
+                );
+            }
+        }
+
+        if ctx.inputs.formula_mode == FormulaMode::Stub {
+            let class_name = Self::stub_class_name(ctx)?;
+            let formula_path = Self::stub_formula_path(ctx)?;
+            if let (Some(service), false) = (&ctx.inputs.service, formula_path.exists()) {
+                // Every stub source shares the same install section.
+                let template = Self::render_stub(&class_name, &StubSource::Placeholder);
+                Self::check_service_binary(&formula_path, &template, &ctx.inputs.tap, service)?;
+            }
+        }
+
+        dependency::check_known(&ctx.inputs.depends_on, ctx.state_store.base_dir())?;
+
+        if ctx.inputs.formula_mode == FormulaMode::Python {
+            let name = Self::python_formula_name(ctx)?;
+            let class_name = formula::class_name(&name);
+            if !formula::is_ruby_constant(&class_name) {
+                anyhow::bail!(
+                    "formula '{}' would get class name '{}', which is not a valid Ruby constant",
+                    name,
+                    class_name
+                );
+            }
+            if let Some(lock) = &ctx.inputs.python_lock {
+                python::read_lock(Path::new(lock))?;
+            }
+            let python = Self::python_formula(ctx)?;
+            let dependency = python.parse().map_err(anyhow::Error::msg)?;
+            dependency::check_known(&[dependency], ctx.state_store.base_dir())
+                .with_context(|| format!("{python} is not an available Python; pass --python"))?;
         }
 
         Ok(())
     }
 
     fn apply(&self, ctx: &mut RunContext) -> Result<()> {
-        let tap_path = Self::tap_path(ctx)?;
+        let tap_path = ctx.state.tap_path()?;
+        let formula_dir = Self::formula_dir(Path::new(tap_path));
+
+        let rendered = match Self::render_formula(ctx, &formula_dir)? {
+            Some(rendered) => rendered,
+            None => {
+                let (formula_name, formula_path) = Self::brew_create(ctx, &formula_dir)?;
+                Self::render(ctx, formula_name, formula_path.clone(), || {
+                    anyhow::bail!("brew create did not write {}", formula_path.display())
+                })?
+            }
+        };
+        Self::write_rendered(ctx, rendered)
+    }
+
+    fn verify(&self, ctx: &mut RunContext) -> Result<VerifyStatus> {
+        let tap_path = ctx.state.tap_path()?;
         let tap_path = Path::new(tap_path);
         let formula_dir = Self::formula_dir(tap_path);
 
         match ctx.inputs.formula_mode {
             FormulaMode::Stub => {
-                fs::create_dir_all(&formula_dir).with_context(|| {
-                    format!(
-                        "failed to create Formula directory: {}",
-                        formula_dir.display()
-                    )
-                })?;
-
                 let formula_path = Self::stub_formula_path(ctx)?;
-                let class_name = Self::formula_class_name(&ctx.inputs.tap);
                 if !formula_path.exists() {
-                    Self::write_stub(&formula_path, &class_name)?;
+                    return Ok(VerifyStatus::Incomplete);
                 }
 
-                Self::set_formula_name(ctx, ctx.inputs.tap.clone())?;
+                let source = Self::read_formula(&formula_path)?;
+                Self::check_stub_class(&formula_path, &source, &Self::stub_class_name(ctx)?)?;
+                Self::check_stub_source(&formula_path, &source, ctx)?;
+                Self::check_additions(&formula_path, &ctx.inputs.tap, ctx)
             }
             FormulaMode::BrewCreate => {
-                let url = ctx.inputs.formula_url.as_deref().unwrap_or("");
-                let formula_name = ctx
-                    .inputs
-                    .formula_name
-                    .clone()
-                    .or_else(|| Self::derive_name_from_url(url))
-                    .ok_or_else(|| {
-                        anyhow::anyhow!(
-                            "formula-name is required when formula-name cannot be derived from URL"
-                        )
-                    })?;
-                println!("    brew create --tap {} {}", ctx.inputs.repo_slug(), url);
-
-                let status = Command::new("brew")
-                    .env("HOMEBREW_EDITOR", "/usr/bin/true")
-                    .env("EDITOR", "/usr/bin/true")
-                    .args([
-                        "create",
-                        "--tap",
-                        &ctx.inputs.repo_slug(),
-                        "--set-name",
-                        &formula_name,
-                        url,
-                    ])
-                    .status()
-                    .context("failed to run brew create")?;
-
-                if !status.success() {
-                    anyhow::bail!("brew create returned non-zero status: {:?}", status.code());
+                if !Self::has_formula_files(&formula_dir)? {
+                    return Ok(VerifyStatus::Incomplete);
                 }
-
-                let names = Self::collect_formula_names(&formula_dir)?;
-                if names.len() == 1 {
-                    Self::set_formula_name(ctx, names[0].clone())?;
-                } else {
-                    Self::set_formula_name(ctx, formula_name)?;
+                match Self::created_formula(ctx, &formula_dir) {
+                    Some((name, formula_path)) => Self::check_additions(&formula_path, &name, ctx),
+                    None => Ok(VerifyStatus::Complete),
                 }
             }
-        }
-
-        Ok(())
-    }
-
-    fn verify(&self, ctx: &mut RunContext) -> Result<VerifyStatus> {
-        let tap_path = Self::tap_path(ctx)?;
-        let tap_path = Path::new(tap_path);
-        let formula_dir = Self::formula_dir(tap_path);
-
-        match ctx.inputs.formula_mode {
-            FormulaMode::Stub => {
-                let formula_path = Self::stub_formula_path(ctx)?;
+            FormulaMode::GithubRelease => {
+                let formula_path = Self::release_formula_path(ctx)?;
                 if formula_path.exists() {
-                    Ok(VerifyStatus::Complete)
+                    let source = Self::read_formula(&formula_path)?;
+                    Self::check_platform_checksums(&formula_path, &source, &[])?;
+                    Self::check_additions(&formula_path, &Self::release_formula_name(ctx)?, ctx)
                 } else {
                     Ok(VerifyStatus::Incomplete)
                 }
             }
-            FormulaMode::BrewCreate => {
-                if Self::has_formula_files(&formula_dir)? {
-                    Ok(VerifyStatus::Complete)
+            FormulaMode::Python => {
+                let formula_path = Self::python_formula_path(ctx)?;
+                if formula_path.exists() {
+                    Self::check_additions(&formula_path, &Self::python_formula_name(ctx)?, ctx)
                 } else {
                     Ok(VerifyStatus::Incomplete)
                 }
             }
         }
     }
+
+    fn plan(&self, ctx: &mut RunContext) -> Result<Vec<PlannedAction>> {
+        let tap_path = ctx.state.tap_path()?;
+        let formula_dir = Self::formula_dir(Path::new(tap_path));
+
+        match Self::render_formula(ctx, &formula_dir)? {
+            Some(rendered) => Ok(Self::planned(rendered)),
+            None => Ok(vec![PlannedAction::command(
+                "brew",
+                Self::brew_create_args(ctx)?,
+            )]),
+        }
+    }
 }
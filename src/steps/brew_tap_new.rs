@@ -2,8 +2,11 @@ use anyhow::{Context, Result};
 use std::path::PathBuf;
 use std::process::Command;
 
+use crate::exec::{self, CommandExt};
+use crate::inputs::InputField;
+use crate::plan::PlannedAction;
 use crate::runner::{Step, VerifyStatus};
-use crate::state::RunContext;
+use crate::state::{RunContext, StateKey};
 
 pub struct BrewTapNewStep;
 
@@ -19,7 +22,7 @@ impl BrewTapNewStep {
 
         let output = Command::new("brew")
             .arg("--repository")
-            .output()
+            .output_within(exec::QUERY_TIMEOUT)
             .context("failed to run brew --repository")?;
 
         if !output.status.success() {
@@ -62,6 +65,18 @@ impl Step for BrewTapNewStep {
         "Create local tap (brew tap-new)"
     }
 
+    fn depends_on(&self) -> &'static [&'static str] {
+        &["preflight"]
+    }
+
+    fn reads_inputs(&self) -> &'static [InputField] {
+        &[InputField::Owner, InputField::RepoName]
+    }
+
+    fn produces(&self) -> &'static [StateKey] {
+        &[StateKey::TapPath]
+    }
+
     fn preflight(&self, _ctx: &mut RunContext) -> Result<()> {
         Ok(())
     }
@@ -70,14 +85,18 @@ impl Step for BrewTapNewStep {
         let repo_slug = ctx.inputs.repo_slug();
         println!("    brew tap-new {}", repo_slug);
 
-        let status = Command::new("brew")
+        let output = Command::new("brew")
             .arg("tap-new")
             .arg(repo_slug)
-            .status()
+            .stream_within(exec::APPLY_TIMEOUT)
             .context("failed to run brew tap-new")?;
 
-        if !status.success() {
-            anyhow::bail!("brew tap-new returned non-zero status: {:?}", status.code());
+        if !output.status.success() {
+            anyhow::bail!(
+                "brew tap-new returned non-zero status: {:?}: {}",
+                output.status.code(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
         }
 
         let _ = Self::ensure_tap_path(ctx)?;
@@ -101,4 +120,12 @@ impl Step for BrewTapNewStep {
 
         Ok(VerifyStatus::Complete)
     }
+
+    fn plan(&self, ctx: &mut RunContext) -> Result<Vec<PlannedAction>> {
+        let _ = Self::ensure_tap_path(ctx)?;
+        Ok(vec![PlannedAction::command(
+            "brew",
+            ["tap-new".to_string(), ctx.inputs.repo_slug()],
+        )])
+    }
 }
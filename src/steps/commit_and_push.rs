@@ -2,8 +2,13 @@ use anyhow::{Context, Result};
 use std::path::Path;
 use std::process::Command;
 
+use crate::exec::{self, CommandExt, RetryPolicy};
+use crate::inputs::InputField;
+use crate::plan::PlannedAction;
 use crate::runner::{Step, VerifyStatus};
-use crate::state::RunContext;
+use crate::state::{RunContext, StateKey};
+
+const COMMIT_MESSAGE: &str = "Update tap files";
 
 pub struct CommitAndPushStep;
 
@@ -12,14 +17,6 @@ impl CommitAndPushStep {
         Self
     }
 
-    fn tap_path<'a>(ctx: &'a RunContext) -> Result<&'a str> {
-        ctx.state
-            .tap_path
-            .as_deref()
-            .filter(|path| !path.trim().is_empty())
-            .ok_or_else(|| anyhow::anyhow!("tap path is not set; brew tap-new must run first"))
-    }
-
     fn ensure_origin(path: &Path) -> Result<()> {
         let output = Command::new("git")
             .args([
@@ -29,7 +26,7 @@ impl CommitAndPushStep {
                 "get-url",
                 "origin",
             ])
-            .output()
+            .output_within(exec::QUERY_TIMEOUT)
             .context("failed to read git remote origin")?;
 
         if output.status.success() {
@@ -43,7 +40,7 @@ impl CommitAndPushStep {
     fn status_info(path: &Path) -> Result<StatusInfo> {
         let porcelain = Command::new("git")
             .args(["-C", path.to_str().unwrap_or(""), "status", "--porcelain"])
-            .output()
+            .output_within(exec::QUERY_TIMEOUT)
             .context("failed to run git status --porcelain")?;
 
         if !porcelain.status.success() {
@@ -55,7 +52,7 @@ impl CommitAndPushStep {
 
         let short = Command::new("git")
             .args(["-C", path.to_str().unwrap_or(""), "status", "-sb"])
-            .output()
+            .output_within(exec::QUERY_TIMEOUT)
             .context("failed to run git status -sb")?;
 
         if !short.status.success() {
@@ -103,7 +100,7 @@ impl CommitAndPushStep {
                     "--abbrev-ref",
                     "HEAD",
                 ])
-                .output()
+                .output_within(exec::QUERY_TIMEOUT)
                 .context("failed to read current branch")?;
 
             if !rev.status.success() {
@@ -124,18 +121,21 @@ impl CommitAndPushStep {
     }
 
     fn commit_changes(path: &Path, message: &str) -> Result<()> {
-        let status = Command::new("git")
+        let output = Command::new("git")
             .args(["-C", path.to_str().unwrap_or(""), "add", "-A"])
-            .status()
+            .output_within(exec::QUERY_TIMEOUT)
             .context("failed to stage changes")?;
 
-        if !status.success() {
-            anyhow::bail!("git add returned non-zero status: {:?}", status.code());
+        if !output.status.success() {
+            anyhow::bail!(
+                "git add returned non-zero status: {:?}",
+                output.status.code()
+            );
         }
 
         let output = Command::new("git")
             .args(["-C", path.to_str().unwrap_or(""), "commit", "-m", message])
-            .output()
+            .output_within(exec::QUERY_TIMEOUT)
             .context("failed to commit changes")?;
 
         if output.status.success() {
@@ -164,13 +164,17 @@ impl CommitAndPushStep {
             args.push(branch);
         }
 
-        let status = Command::new("git")
+        let output = Command::new("git")
             .args(args)
-            .status()
+            .stream_within(exec::APPLY_TIMEOUT)
             .context("failed to push changes")?;
 
-        if !status.success() {
-            anyhow::bail!("git push returned non-zero status: {:?}", status.code());
+        if !output.status.success() {
+            anyhow::bail!(
+                "git push returned non-zero status: {:?}: {}",
+                output.status.code(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
         }
 
         Ok(())
@@ -192,8 +196,20 @@ impl Step for CommitAndPushStep {
         "Commit and push changes"
     }
 
+    fn depends_on(&self) -> &'static [&'static str] {
+        &["gh_repo_create", "add_formula"]
+    }
+
+    fn reads_inputs(&self) -> &'static [InputField] {
+        &[InputField::Branch]
+    }
+
+    fn consumes(&self) -> &'static [StateKey] {
+        &[StateKey::TapPath]
+    }
+
     fn preflight(&self, ctx: &mut RunContext) -> Result<()> {
-        let tap_path = Self::tap_path(ctx)?;
+        let tap_path = ctx.state.tap_path()?;
         let path = Path::new(tap_path);
 
         if !path.exists() {
@@ -209,8 +225,8 @@ impl Step for CommitAndPushStep {
     }
 
     fn apply(&self, ctx: &mut RunContext) -> Result<()> {
-        let tap_path = Self::tap_path(ctx)?;
-        let path = Path::new(tap_path);
+        let tap_path = ctx.state.tap_path()?.to_string();
+        let path = Path::new(&tap_path);
 
         let mut status = Self::status_info(path)?;
         if status.behind > 0 {
@@ -218,7 +234,7 @@ impl Step for CommitAndPushStep {
         }
 
         if status.dirty {
-            Self::commit_changes(path, "Update tap files")?;
+            Self::commit_changes(path, COMMIT_MESSAGE)?;
         }
 
         status = Self::status_info(path)?;
@@ -227,14 +243,16 @@ impl Step for CommitAndPushStep {
         }
 
         if status.ahead > 0 || !status.has_upstream {
-            Self::push_changes(path, &status.branch, !status.has_upstream)?;
+            exec::with_retry(ctx, &RetryPolicy::network(), "git push", |_| {
+                Self::push_changes(path, &status.branch, !status.has_upstream)
+            })?;
         }
 
         Ok(())
     }
 
     fn verify(&self, ctx: &mut RunContext) -> Result<VerifyStatus> {
-        let tap_path = Self::tap_path(ctx)?;
+        let tap_path = ctx.state.tap_path()?;
         let path = Path::new(tap_path);
 
         let status = Self::status_info(path)?;
@@ -248,6 +266,17 @@ impl Step for CommitAndPushStep {
 
         Ok(VerifyStatus::Complete)
     }
+
+    fn plan(&self, ctx: &mut RunContext) -> Result<Vec<PlannedAction>> {
+        let tap_path = ctx.state.tap_path()?;
+        let branch = &ctx.inputs.branch;
+
+        Ok(vec![
+            PlannedAction::command("git", ["-C", tap_path, "add", "-A"]),
+            PlannedAction::command("git", ["-C", tap_path, "commit", "-m", COMMIT_MESSAGE]),
+            PlannedAction::command("git", ["-C", tap_path, "push", "-u", "origin", branch]),
+        ])
+    }
 }
 
 struct StatusInfo {
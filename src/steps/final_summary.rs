@@ -1,8 +1,11 @@
 use anyhow::Result;
 
-use crate::inputs::FormulaMode;
+use crate::inputs::{FormulaMode, InputField, SourceKind};
+use crate::license::Confidence;
+use crate::plan::PlannedAction;
 use crate::runner::{Step, VerifyStatus};
-use crate::state::RunContext;
+use crate::source_url;
+use crate::state::{RunContext, StateKey};
 
 pub struct FinalSummaryStep;
 
@@ -27,6 +30,26 @@ impl Step for FinalSummaryStep {
         "Final summary"
     }
 
+    fn depends_on(&self) -> &'static [&'static str] {
+        &["validate_tap"]
+    }
+
+    fn reads_inputs(&self) -> &'static [InputField] {
+        &[
+            InputField::Owner,
+            InputField::Tap,
+            InputField::RepoName,
+            InputField::FormulaMode,
+            InputField::FormulaUrl,
+            InputField::SourceKind,
+            InputField::Service,
+        ]
+    }
+
+    fn produces(&self) -> &'static [StateKey] {
+        &[StateKey::SummaryPrinted]
+    }
+
     fn preflight(&self, _ctx: &mut RunContext) -> Result<()> {
         Ok(())
     }
@@ -55,19 +78,66 @@ impl Step for FinalSummaryStep {
             FormulaMode::Stub => {
                 println!("  Stub formula: {}/Formula/{}.rb", tap_path, ctx.inputs.tap);
             }
-            FormulaMode::BrewCreate => {
+            FormulaMode::BrewCreate | FormulaMode::GithubRelease | FormulaMode::Python => {
                 println!("  Formula directory: {}/Formula", tap_path);
+                let from_tarball = ctx.inputs.formula_mode != FormulaMode::GithubRelease;
+                if let Some(url) = ctx.inputs.formula_url.as_ref().filter(|_| from_tarball) {
+                    let source = source_url::analyze(url);
+                    match &source.version {
+                        Some(version) => {
+                            println!("  Source: {} (version {})", source.hosting, version)
+                        }
+                        None => println!("  Source: {} (version not detected)", source.hosting),
+                    }
+                }
             }
         }
+        match &ctx.state.license {
+            Some(detection) => match (detection.confidence, &detection.note) {
+                (Confidence::Low, Some(note)) => println!(
+                    "  License: {} (from {}, low confidence: {})",
+                    detection.license, detection.source, note
+                ),
+                _ => println!(
+                    "  License: {} (from {})",
+                    detection.license, detection.source
+                ),
+            },
+            None => println!("  License: not detected; pass --license or edit the formula"),
+        }
 
         println!("\nNext steps");
         println!("  - Edit the formula and replace the TODO fields.");
 
         let install_formula = ctx.state.formula_name.as_deref().unwrap_or(&ctx.inputs.tap);
-        println!(
-            "  - brew install {}/{} (once the formula URL and sha256 are valid)",
-            tap_name, install_formula
-        );
+        match ctx.inputs.source_kind {
+            SourceKind::Tarball => println!(
+                "  - brew install {}/{} (once the formula URL and sha256 are valid)",
+                tap_name, install_formula
+            ),
+            SourceKind::GitTag => println!("  - brew install {}/{}", tap_name, install_formula),
+            SourceKind::Head => println!(
+                "  - brew install --HEAD {}/{} (head-only formulae have no stable version)",
+                tap_name, install_formula
+            ),
+        }
+        if let Some(detection) = ctx
+            .state
+            .license
+            .as_ref()
+            .filter(|detection| detection.confidence == Confidence::Low)
+        {
+            println!(
+                "  - Check the license before publishing: {}",
+                detection
+                    .note
+                    .as_deref()
+                    .unwrap_or("detected with low confidence")
+            );
+        }
+        if ctx.inputs.service.is_some() {
+            println!("  - brew services start {}/{}", tap_name, install_formula);
+        }
 
         ctx.state.summary_printed = true;
         ctx.persist()?;
@@ -82,4 +152,8 @@ impl Step for FinalSummaryStep {
             Ok(VerifyStatus::Incomplete)
         }
     }
+
+    fn plan(&self, _ctx: &mut RunContext) -> Result<Vec<PlannedAction>> {
+        Ok(Vec::new())
+    }
 }
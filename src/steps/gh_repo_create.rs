@@ -3,9 +3,11 @@ use serde::Deserialize;
 use std::path::Path;
 use std::process::Command;
 
-use crate::inputs::Visibility;
+use crate::exec::{self, CommandExt, RetryPolicy};
+use crate::inputs::{InputField, Visibility};
+use crate::plan::PlannedAction;
 use crate::runner::{Step, VerifyStatus};
-use crate::state::RunContext;
+use crate::state::{RunContext, StateKey};
 
 pub struct GhRepoCreateStep;
 
@@ -14,18 +16,10 @@ impl GhRepoCreateStep {
         Self
     }
 
-    fn tap_path<'a>(ctx: &'a RunContext) -> Result<&'a str> {
-        ctx.state
-            .tap_path
-            .as_deref()
-            .filter(|path| !path.trim().is_empty())
-            .ok_or_else(|| anyhow::anyhow!("tap path is not set; brew tap-new must run first"))
-    }
-
     fn repo_exists(repo_slug: &str) -> Result<bool> {
         let output = Command::new("gh")
             .args(["repo", "view", repo_slug, "--json", "name"])
-            .output()
+            .output_within(exec::QUERY_TIMEOUT)
             .context("failed to run gh repo view")?;
 
         if output.status.success() {
@@ -43,7 +37,7 @@ impl GhRepoCreateStep {
     fn fetch_repo_urls(repo_slug: &str) -> Result<RepoUrls> {
         let output = Command::new("gh")
             .args(["repo", "view", repo_slug, "--json", "sshUrl,url"])
-            .output()
+            .output_within(exec::QUERY_TIMEOUT)
             .context("failed to run gh repo view")?;
 
         if !output.status.success() {
@@ -66,7 +60,7 @@ impl GhRepoCreateStep {
                 "get-url",
                 remote,
             ])
-            .output()
+            .output_within(exec::QUERY_TIMEOUT)
             .context("failed to query git remote")?;
 
         if output.status.success() {
@@ -84,6 +78,13 @@ impl GhRepoCreateStep {
         anyhow::bail!("git remote get-url failed: {}", stderr.trim())
     }
 
+    fn visibility_flag(visibility: Visibility) -> &'static str {
+        match visibility {
+            Visibility::Public => "--public",
+            Visibility::Private => "--private",
+        }
+    }
+
     fn ensure_branch(path: &Path, branch: &str) -> Result<()> {
         let output = Command::new("git")
             .args([
@@ -93,7 +94,7 @@ impl GhRepoCreateStep {
                 "--abbrev-ref",
                 "HEAD",
             ])
-            .output()
+            .output_within(exec::QUERY_TIMEOUT)
             .context("failed to read current git branch")?;
 
         if !output.status.success() {
@@ -106,15 +107,66 @@ impl GhRepoCreateStep {
             return Ok(());
         }
 
-        let status = Command::new("git")
+        let output = Command::new("git")
             .args(["-C", path.to_str().unwrap_or(""), "branch", "-M", branch])
-            .status()
+            .output_within(exec::QUERY_TIMEOUT)
             .context("failed to rename git branch")?;
 
-        if !status.success() {
+        if !output.status.success() {
             anyhow::bail!(
                 "git branch -M returned non-zero status: {:?}",
-                status.code()
+                output.status.code()
+            );
+        }
+
+        Ok(())
+    }
+
+    fn create_repo(repo_slug: &str, tap_path: &str, visibility_flag: &str) -> Result<()> {
+        let output = Command::new("gh")
+            .args([
+                "repo",
+                "create",
+                repo_slug,
+                "--source",
+                tap_path,
+                "--push",
+                "--remote",
+                "origin",
+                visibility_flag,
+            ])
+            .stream_within(exec::APPLY_TIMEOUT)
+            .context("failed to run gh repo create")?;
+
+        if !output.status.success() {
+            anyhow::bail!(
+                "gh repo create returned non-zero status: {:?}: {}",
+                output.status.code(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        Ok(())
+    }
+
+    fn push_branch(path: &Path, branch: &str) -> Result<()> {
+        let output = Command::new("git")
+            .args([
+                "-C",
+                path.to_str().unwrap_or(""),
+                "push",
+                "-u",
+                "origin",
+                branch,
+            ])
+            .stream_within(exec::APPLY_TIMEOUT)
+            .context("failed to run git push")?;
+
+        if !output.status.success() {
+            anyhow::bail!(
+                "git push returned non-zero status: {:?}: {}",
+                output.status.code(),
+                String::from_utf8_lossy(&output.stderr).trim()
             );
         }
 
@@ -137,8 +189,25 @@ impl Step for GhRepoCreateStep {
         "Create GitHub repo and push"
     }
 
+    fn depends_on(&self) -> &'static [&'static str] {
+        &["brew_tap_new"]
+    }
+
+    fn reads_inputs(&self) -> &'static [InputField] {
+        &[
+            InputField::Owner,
+            InputField::RepoName,
+            InputField::Visibility,
+            InputField::Branch,
+        ]
+    }
+
+    fn consumes(&self) -> &'static [StateKey] {
+        &[StateKey::TapPath]
+    }
+
     fn preflight(&self, ctx: &mut RunContext) -> Result<()> {
-        let tap_path = Self::tap_path(ctx)?;
+        let tap_path = ctx.state.tap_path()?;
         let path = Path::new(tap_path);
 
         if !path.exists() {
@@ -153,49 +222,32 @@ impl Step for GhRepoCreateStep {
     }
 
     fn apply(&self, ctx: &mut RunContext) -> Result<()> {
-        let tap_path = Self::tap_path(ctx)?;
-        let path = Path::new(tap_path);
+        let tap_path = ctx.state.tap_path()?.to_string();
+        let path = Path::new(&tap_path);
         let repo_slug = ctx.inputs.repo_slug();
+        let branch = ctx.inputs.branch.clone();
 
-        Self::ensure_branch(path, &ctx.inputs.branch)?;
+        Self::ensure_branch(path, &branch)?;
 
-        let visibility_flag = match ctx.inputs.visibility {
-            Visibility::Public => "--public",
-            Visibility::Private => "--private",
-        };
+        let visibility_flag = Self::visibility_flag(ctx.inputs.visibility);
 
         println!(
             "    gh repo create {} --source {} --push",
             repo_slug, tap_path
         );
 
-        let status = Command::new("gh")
-            .args([
-                "repo",
-                "create",
-                &repo_slug,
-                "--source",
-                tap_path,
-                "--push",
-                "--remote",
-                "origin",
-                visibility_flag,
-            ])
-            .status()
-            .context("failed to run gh repo create")?;
-
-        if !status.success() {
-            anyhow::bail!(
-                "gh repo create returned non-zero status: {:?}",
-                status.code()
-            );
-        }
+        exec::with_retry(ctx, &RetryPolicy::network(), "gh repo create", |_| {
+            // A previous attempt may have created the repo and failed while pushing.
+            if Self::repo_exists(&repo_slug)? && Self::git_remote_url(path, "origin")?.is_some() {
+                return Self::push_branch(path, &branch);
+            }
 
-        Ok(())
+            Self::create_repo(&repo_slug, &tap_path, visibility_flag)
+        })
     }
 
     fn verify(&self, ctx: &mut RunContext) -> Result<VerifyStatus> {
-        let tap_path = Self::tap_path(ctx)?;
+        let tap_path = ctx.state.tap_path()?;
         let path = Path::new(tap_path);
         let repo_slug = ctx.inputs.repo_slug();
 
@@ -228,6 +280,34 @@ impl Step for GhRepoCreateStep {
 
         Ok(VerifyStatus::Complete)
     }
+
+    fn plan(&self, ctx: &mut RunContext) -> Result<Vec<PlannedAction>> {
+        let tap_path = ctx.state.tap_path()?;
+        let repo_slug = ctx.inputs.repo_slug();
+        let visibility_flag = Self::visibility_flag(ctx.inputs.visibility);
+
+        Ok(vec![
+            PlannedAction::command("git", ["-C", tap_path, "branch", "-M", &ctx.inputs.branch]),
+            PlannedAction::create_remote(
+                "GitHub repository",
+                format!("{} ({})", repo_slug, &visibility_flag[2..]),
+            ),
+            PlannedAction::command(
+                "gh",
+                [
+                    "repo",
+                    "create",
+                    &repo_slug,
+                    "--source",
+                    tap_path,
+                    "--push",
+                    "--remote",
+                    "origin",
+                    visibility_flag,
+                ],
+            ),
+        ])
+    }
 }
 
 #[derive(Debug, Deserialize)]
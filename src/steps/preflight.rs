@@ -2,6 +2,8 @@ use anyhow::{Context, Result};
 use std::io::ErrorKind;
 use std::process::Command;
 
+use crate::exec::{self, CommandExt};
+use crate::plan::PlannedAction;
 use crate::runner::{Step, VerifyStatus};
 use crate::state::RunContext;
 
@@ -31,7 +33,7 @@ impl PreflightStep {
                     let not_found = err.chain().any(|cause| {
                         cause
                             .downcast_ref::<std::io::Error>()
-                            .map_or(false, |io_err| io_err.kind() == ErrorKind::NotFound)
+                            .is_some_and(|io_err| io_err.kind() == ErrorKind::NotFound)
                     });
 
                     if not_found {
@@ -82,6 +84,10 @@ impl Step for PreflightStep {
         self.check_required()?;
         Ok(VerifyStatus::Complete)
     }
+
+    fn plan(&self, _ctx: &mut RunContext) -> Result<Vec<PlannedAction>> {
+        Ok(Vec::new())
+    }
 }
 
 struct RequiredCommand {
@@ -97,15 +103,17 @@ impl RequiredCommand {
 }
 
 fn check_command(name: &str, args: &[&str]) -> Result<()> {
-    let output = Command::new(name).args(args).output();
+    let result = Command::new(name)
+        .args(args)
+        .output_within(exec::QUERY_TIMEOUT)?;
 
-    match output {
-        Ok(result) if result.status.success() => Ok(()),
-        Ok(result) => anyhow::bail!(
+    if !result.status.success() {
+        anyhow::bail!(
             "{} returned non-zero status: {:?}",
             name,
             result.status.code()
-        ),
-        Err(err) => Err(err).context(format!("failed to execute {name}")),
+        );
     }
+
+    Ok(())
 }
@@ -1,6 +1,9 @@
 use anyhow::{Context, Result};
 use std::process::Command;
 
+use crate::exec::{self, CommandExt, RetryPolicy};
+use crate::inputs::InputField;
+use crate::plan::PlannedAction;
 use crate::runner::{Step, VerifyStatus};
 use crate::state::RunContext;
 
@@ -37,7 +40,7 @@ impl ValidateTapStep {
     fn is_tapped(identifier: &str) -> Result<bool> {
         let output = Command::new("brew")
             .arg("tap")
-            .output()
+            .output_within(exec::QUERY_TIMEOUT)
             .context("failed to run brew tap")?;
 
         if !output.status.success() {
@@ -65,6 +68,14 @@ impl Step for ValidateTapStep {
         "Validate tap is registered"
     }
 
+    fn depends_on(&self) -> &'static [&'static str] {
+        &["commit_and_push"]
+    }
+
+    fn reads_inputs(&self) -> &'static [InputField] {
+        &[InputField::Owner, InputField::Tap, InputField::RepoName]
+    }
+
     fn preflight(&self, _ctx: &mut RunContext) -> Result<()> {
         Ok(())
     }
@@ -73,13 +84,17 @@ impl Step for ValidateTapStep {
         let identifier = Self::preferred_tap(ctx);
         println!("    brew tap {}", identifier);
 
-        let status = Command::new("brew")
+        let output = Command::new("brew")
             .args(["tap", &identifier])
-            .status()
+            .stream_within(exec::APPLY_TIMEOUT)
             .context("failed to run brew tap")?;
 
-        if !status.success() {
-            anyhow::bail!("brew tap returned non-zero status: {:?}", status.code());
+        if !output.status.success() {
+            anyhow::bail!(
+                "brew tap returned non-zero status: {:?}: {}",
+                output.status.code(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
         }
 
         Ok(())
@@ -95,4 +110,13 @@ impl Step for ValidateTapStep {
 
         Ok(VerifyStatus::Incomplete)
     }
+
+    fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy::network()
+    }
+
+    fn plan(&self, ctx: &mut RunContext) -> Result<Vec<PlannedAction>> {
+        let identifier = Self::preferred_tap(ctx);
+        Ok(vec![PlannedAction::command("brew", ["tap", &identifier])])
+    }
 }
@@ -0,0 +1,709 @@
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+use std::cmp::Ordering;
+use std::process::Command;
+
+use crate::exec::{self, CommandExt};
+use crate::formula::FormulaSource;
+use crate::source_url::{self, Hosting};
+
+const GITHUB_API_ENV: &str = "HOMEBREW_TAP_SETUP_GITHUB_API_URL";
+const GITHUB_URL_ENV: &str = "HOMEBREW_TAP_SETUP_GITHUB_URL";
+const GITLAB_URL_ENV: &str = "HOMEBREW_TAP_SETUP_GITLAB_URL";
+const PYPI_URL_ENV: &str = "HOMEBREW_TAP_SETUP_PYPI_URL";
+const NPM_REGISTRY_ENV: &str = "HOMEBREW_TAP_SETUP_NPM_REGISTRY";
+const CRATES_URL_ENV: &str = "HOMEBREW_TAP_SETUP_CRATES_URL";
+const SOURCEFORGE_URL_ENV: &str = "HOMEBREW_TAP_SETUP_SOURCEFORGE_URL";
+
+/// Fetches a URL and returns the body; non-2xx responses are errors.
+pub trait HttpClient {
+    fn get(&self, url: &str) -> Result<String>;
+}
+
+/// `HttpClient` backed by the `curl` binary, which CI images already have.
+pub struct CurlClient;
+
+impl HttpClient for CurlClient {
+    fn get(&self, url: &str) -> Result<String> {
+        let output = Command::new("curl")
+            .args([
+                "--silent",
+                "--show-error",
+                "--location",
+                "--fail",
+                "--user-agent",
+                "homebrew-tap-setup",
+                url,
+            ])
+            .output_within(exec::QUERY_TIMEOUT)?;
+
+        if !output.status.success() {
+            bail!(
+                "GET {} failed: {}",
+                url,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+/// Base URLs for the services the strategies query, overridable so a local
+/// fixture server can stand in for them.
+#[derive(Debug, Clone)]
+pub struct Endpoints {
+    pub github_api: String,
+    pub github: String,
+    pub gitlab: String,
+    pub pypi: String,
+    pub npm_registry: String,
+    pub crates_io: String,
+    pub sourceforge: String,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Self {
+            github_api: "https://api.github.com".to_string(),
+            github: "https://github.com".to_string(),
+            gitlab: "https://gitlab.com".to_string(),
+            pypi: "https://pypi.org".to_string(),
+            npm_registry: "https://registry.npmjs.org".to_string(),
+            crates_io: "https://crates.io".to_string(),
+            sourceforge: "https://sourceforge.net".to_string(),
+        }
+    }
+}
+
+impl Endpoints {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |name: &str, default: String| {
+            std::env::var(name)
+                .ok()
+                .map(|value| value.trim_end_matches('/').to_string())
+                .filter(|value| !value.is_empty())
+                .unwrap_or(default)
+        };
+
+        Self {
+            github_api: var(GITHUB_API_ENV, defaults.github_api),
+            github: var(GITHUB_URL_ENV, defaults.github),
+            gitlab: var(GITLAB_URL_ENV, defaults.gitlab),
+            pypi: var(PYPI_URL_ENV, defaults.pypi),
+            npm_registry: var(NPM_REGISTRY_ENV, defaults.npm_registry),
+            crates_io: var(CRATES_URL_ENV, defaults.crates_io),
+            sourceforge: var(SOURCEFORGE_URL_ENV, defaults.sourceforge),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    GithubLatest,
+    Git,
+    Pypi,
+    Npm,
+    Crate,
+    SourceForge,
+    PageMatch,
+}
+
+impl Strategy {
+    pub fn name(self) -> &'static str {
+        match self {
+            Strategy::GithubLatest => "github_latest",
+            Strategy::Git => "git",
+            Strategy::Pypi => "pypi",
+            Strategy::Npm => "npm",
+            Strategy::Crate => "crate",
+            Strategy::SourceForge => "sourceforge",
+            Strategy::PageMatch => "page_match",
+        }
+    }
+}
+
+pub struct Checker<'a> {
+    http: &'a dyn HttpClient,
+    endpoints: Endpoints,
+}
+
+impl<'a> Checker<'a> {
+    pub fn new(http: &'a dyn HttpClient, endpoints: Endpoints) -> Self {
+        Self { http, endpoints }
+    }
+
+    /// Picks a strategy from the formula's `livecheck` block, falling back to
+    /// where its source is hosted. `None` means there is no native strategy.
+    pub fn strategy(&self, formula: &FormulaSource) -> Option<Strategy> {
+        let spec = formula.livecheck.as_ref();
+        if let Some(strategy) = spec.and_then(|spec| spec.strategy.as_deref()) {
+            return match strategy {
+                "github_latest" | "github_releases" => Some(Strategy::GithubLatest),
+                "git" => Some(Strategy::Git),
+                "pypi" => Some(Strategy::Pypi),
+                "npm" => Some(Strategy::Npm),
+                "crate" => Some(Strategy::Crate),
+                "sourceforge" => Some(Strategy::SourceForge),
+                "page_match" => Some(Strategy::PageMatch),
+                _ => None,
+            };
+        }
+
+        let url = formula.livecheck_url()?;
+        match source_url::analyze(&url).hosting {
+            Hosting::GitHubRelease => Some(Strategy::GithubLatest),
+            Hosting::GitHubArchive | Hosting::GitLab => Some(Strategy::Git),
+            Hosting::PyPi => Some(Strategy::Pypi),
+            Hosting::Npm => Some(Strategy::Npm),
+            Hosting::CratesIo => Some(Strategy::Crate),
+            Hosting::SourceForge => Some(Strategy::SourceForge),
+            Hosting::Generic if spec.is_some_and(|spec| spec.regex.is_some()) => {
+                Some(Strategy::PageMatch)
+            }
+            _ => None,
+        }
+    }
+
+    /// Lists a remote's tags over git's smart HTTP protocol.
+    fn git_tags(&self, remote: &str) -> Result<Vec<String>> {
+        let remote = remote.trim_end_matches('/');
+        let body = self
+            .http
+            .get(&format!("{remote}/info/refs?service=git-upload-pack"))?;
+        advertised_tags(&body).with_context(|| format!("failed to list tags of {remote}"))
+    }
+
+    pub fn latest(&self, formula: &FormulaSource, strategy: Strategy) -> Result<Option<String>> {
+        let url = formula
+            .livecheck_url()
+            .context("formula has no URL to check")?;
+        let source = source_url::analyze(&url);
+        let regex = formula
+            .livecheck
+            .as_ref()
+            .and_then(|spec| spec.regex.as_deref())
+            .map(ruby_regex)
+            .transpose()?;
+        let name = source.name.clone().unwrap_or_default();
+
+        match strategy {
+            Strategy::GithubLatest => {
+                let repo = source
+                    .repository
+                    .context("github_latest needs a github.com URL")?;
+                let body = self.http.get(&format!(
+                    "{}/repos/{}/releases/latest",
+                    self.endpoints.github_api, repo
+                ))?;
+                let release: GithubRelease =
+                    serde_json::from_str(&body).context("failed to parse GitHub release")?;
+                Ok(match &regex {
+                    Some(regex) => capture(regex, &release.tag_name),
+                    None => source_url::version_from_tag(&release.tag_name, &name),
+                })
+            }
+            Strategy::Git => {
+                let remote = match (source.hosting, source.repository) {
+                    (Hosting::GitHubArchive | Hosting::GitHubRelease, Some(repo)) => {
+                        format!("{}/{}.git", self.endpoints.github, repo)
+                    }
+                    (Hosting::GitLab, Some(repo)) => {
+                        format!("{}/{}.git", self.endpoints.gitlab, repo)
+                    }
+                    _ => url.clone(),
+                };
+                let versions = self
+                    .git_tags(&remote)?
+                    .iter()
+                    .filter_map(|tag| match &regex {
+                        Some(regex) => capture(regex, tag),
+                        None => source_url::version_from_tag(tag, &name),
+                    })
+                    .collect::<Vec<_>>();
+                Ok(newest(versions))
+            }
+            Strategy::Pypi => {
+                let project = source.formula_name().context("no PyPI project name")?;
+                let body = self
+                    .http
+                    .get(&format!("{}/pypi/{}/json", self.endpoints.pypi, project))?;
+                let package: PypiPackage =
+                    serde_json::from_str(&body).context("failed to parse PyPI response")?;
+                Ok(Some(package.info.version))
+            }
+            Strategy::Npm => {
+                let package = npm_package(&url).context("no npm package in URL")?;
+                let body = self.http.get(&format!(
+                    "{}/{}/latest",
+                    self.endpoints.npm_registry, package
+                ))?;
+                let latest: NpmVersion =
+                    serde_json::from_str(&body).context("failed to parse npm response")?;
+                Ok(Some(latest.version))
+            }
+            Strategy::Crate => {
+                let name = source.name.context("no crate name in URL")?;
+                let body = self.http.get(&format!(
+                    "{}/api/v1/crates/{}",
+                    self.endpoints.crates_io, name
+                ))?;
+                let package: CratesIoCrate =
+                    serde_json::from_str(&body).context("failed to parse crates.io response")?;
+                Ok(package.krate.max_stable_version)
+            }
+            Strategy::SourceForge => {
+                let project = source.name.context("no SourceForge project in URL")?;
+                let body = self.http.get(&format!(
+                    "{}/projects/{}/rss",
+                    self.endpoints.sourceforge, project
+                ))?;
+                // Homebrew's default: versions in the file paths of the feed.
+                let regex = match regex {
+                    Some(regex) => regex,
+                    None => Regex::new(&format!(
+                        r"(?i)url=.*?/{}/files/.*?[-_/](\d+(?:[-.]\d+)+)[-_/%.]",
+                        regex::escape(&project)
+                    ))?,
+                };
+                let versions = regex
+                    .captures_iter(&body)
+                    .filter_map(|caps| caps.get(1).map(|found| found.as_str().to_string()))
+                    .collect::<Vec<_>>();
+                Ok(newest(versions))
+            }
+            Strategy::PageMatch => {
+                let regex = regex.context("page_match needs a regex in the livecheck block")?;
+                let body = self.http.get(&url)?;
+                let versions = regex
+                    .captures_iter(&body)
+                    .filter_map(|caps| caps.get(1).map(|found| found.as_str().to_string()))
+                    .collect::<Vec<_>>();
+                Ok(newest(versions))
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct GithubRelease {
+    tag_name: String,
+}
+
+#[derive(Deserialize)]
+struct PypiPackage {
+    info: PypiInfo,
+}
+
+#[derive(Deserialize)]
+struct PypiInfo {
+    version: String,
+}
+
+#[derive(Deserialize)]
+struct CratesIoCrate {
+    #[serde(rename = "crate")]
+    krate: CratesIoInfo,
+}
+
+#[derive(Deserialize)]
+struct CratesIoInfo {
+    max_stable_version: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct NpmVersion {
+    version: String,
+}
+
+/// Tag names from git's smart HTTP ref advertisement, the response to
+/// `GET <remote>/info/refs?service=git-upload-pack`: pkt-lines of
+/// `<sha> <ref>`, the first followed by a NUL and the server capabilities.
+fn advertised_tags(body: &str) -> Result<Vec<String>> {
+    let mut tags: Vec<String> = Vec::new();
+    let mut rest = body;
+    while !rest.is_empty() {
+        let length = rest
+            .get(..4)
+            .and_then(|length| usize::from_str_radix(length, 16).ok())
+            .context("malformed git ref advertisement")?;
+        if length == 0 {
+            rest = &rest[4..];
+            continue;
+        }
+        let line = rest
+            .get(4..length)
+            .context("malformed git ref advertisement")?;
+        rest = &rest[length..];
+
+        let line = line.split('\0').next().unwrap_or(line).trim_end();
+        let tag = line
+            .split_once(' ')
+            .and_then(|(_, reference)| reference.strip_prefix("refs/tags/"))
+            .map(|tag| tag.trim_end_matches("^{}").to_string());
+        if let Some(tag) = tag.filter(|tag| !tags.contains(tag)) {
+            tags.push(tag);
+        }
+    }
+
+    Ok(tags)
+}
+
+/// The commit a tag points at (peeling annotated tags), as Homebrew's
+/// `revision:` expects.
+pub fn tag_revision(remote: &str, tag: &str) -> Result<String> {
+    let reference = format!("refs/tags/{tag}");
+    let peeled = format!("{reference}^{{}}");
+    let output = Command::new("git")
+        .args(["ls-remote", remote, &reference, &peeled])
+        .output_within(exec::QUERY_TIMEOUT)
+        .context("failed to run git ls-remote")?;
+    if !output.status.success() {
+        bail!(
+            "git ls-remote {} failed: {}",
+            remote,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let refs: Vec<(&str, &str)> = stdout
+        .lines()
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        .map(|(sha, name)| (sha, name.trim()))
+        .collect();
+    let find = |name: &str| refs.iter().find(|(_, found)| *found == name);
+    match find(&peeled).or_else(|| find(&reference)) {
+        Some((sha, _)) => Ok(sha.to_string()),
+        None => bail!("tag {} not found in {}", tag, remote),
+    }
+}
+
+/// `registry.npmjs.org/@scope/pkg/-/pkg-1.0.tgz` -> `@scope/pkg`.
+fn npm_package(url: &str) -> Option<String> {
+    let path = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let (_, path) = path.split_once('/')?;
+    let (package, _) = path.split_once("/-/")?;
+    Some(package.to_string())
+}
+
+/// Converts a Ruby regex literal (`/.../i`) to a Rust regex.
+fn ruby_regex(literal: &str) -> Result<Regex> {
+    let body = literal
+        .strip_prefix('/')
+        .and_then(|rest| rest.rsplit_once('/'))
+        .map(|(pattern, flags)| {
+            let mut prefix = String::new();
+            for flag in flags.chars() {
+                match flag {
+                    'i' => prefix.push_str("(?i)"),
+                    'm' => prefix.push_str("(?s)"),
+                    'x' => prefix.push_str("(?x)"),
+                    _ => {}
+                }
+            }
+            format!("{prefix}{}", pattern.replace("\\/", "/"))
+        })
+        .unwrap_or_else(|| literal.to_string());
+
+    Regex::new(&body).with_context(|| format!("unsupported livecheck regex {literal}"))
+}
+
+fn capture(regex: &Regex, text: &str) -> Option<String> {
+    regex
+        .captures(text)
+        .and_then(|caps| caps.get(1).or_else(|| caps.get(0)))
+        .map(|found| found.as_str().to_string())
+}
+
+fn newest(versions: Vec<String>) -> Option<String> {
+    versions
+        .into_iter()
+        .filter(|version| !is_prerelease(version))
+        .max_by(|a, b| compare_versions(a, b))
+}
+
+fn is_prerelease(version: &str) -> bool {
+    let lower = version.to_ascii_lowercase();
+    ["alpha", "beta", "rc", "pre", "dev"]
+        .iter()
+        .any(|marker| lower.contains(marker))
+}
+
+/// Compares dotted versions numerically where both sides have numbers
+/// (`1.10` > `1.9`), falling back to text for the rest.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let parts = |version: &str| -> Vec<String> {
+        version
+            .split(['.', '-', '_', '+'])
+            .filter(|part| !part.is_empty())
+            .map(str::to_string)
+            .collect()
+    };
+    let (left, right) = (parts(a), parts(b));
+
+    for index in 0..left.len().max(right.len()) {
+        let ordering = match (left.get(index), right.get(index)) {
+            (Some(l), Some(r)) => match (l.parse::<u64>(), r.parse::<u64>()) {
+                (Ok(l), Ok(r)) => l.cmp(&r),
+                _ => l.cmp(r),
+            },
+            (Some(_), None) => Ordering::Greater,
+            (None, Some(_)) => Ordering::Less,
+            (None, None) => Ordering::Equal,
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    Ordering::Equal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formula;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+
+    /// Serves canned bodies by URL and records what was requested.
+    #[derive(Default)]
+    struct FakeHttp {
+        responses: HashMap<String, String>,
+        requested: RefCell<Vec<String>>,
+    }
+
+    impl FakeHttp {
+        fn with(mut self, url: &str, body: &str) -> Self {
+            self.responses.insert(url.to_string(), body.to_string());
+            self
+        }
+    }
+
+    impl HttpClient for FakeHttp {
+        fn get(&self, url: &str) -> Result<String> {
+            self.requested.borrow_mut().push(url.to_string());
+            match self.responses.get(url) {
+                Some(body) => Ok(body.clone()),
+                None => bail!("GET {} failed: 404", url),
+            }
+        }
+    }
+
+    fn endpoints() -> Endpoints {
+        Endpoints {
+            github_api: "http://fixture/github-api".to_string(),
+            github: "http://fixture/github".to_string(),
+            gitlab: "http://fixture/gitlab".to_string(),
+            pypi: "http://fixture/pypi".to_string(),
+            npm_registry: "http://fixture/npm".to_string(),
+            crates_io: "http://fixture/crates".to_string(),
+            sourceforge: "http://fixture/sourceforge".to_string(),
+        }
+    }
+
+    fn formula(body: &str) -> FormulaSource {
+        formula::parse(&format!("class Tool < Formula\n{body}\nend\n"))
+    }
+
+    fn check(http: &FakeHttp, body: &str) -> (Strategy, Option<String>) {
+        let checker = Checker::new(http, endpoints());
+        let source = formula(body);
+        let strategy = checker.strategy(&source).expect("a native strategy");
+        let latest = checker.latest(&source, strategy).expect("latest version");
+        (strategy, latest)
+    }
+
+    /// A smart HTTP ref advertisement listing `refs`.
+    fn advertisement(refs: &[&str]) -> String {
+        let pkt = |line: &str| format!("{:04x}{}", line.len() + 4, line);
+        let mut body = pkt("# service=git-upload-pack\n");
+        body.push_str("0000");
+        for (index, reference) in refs.iter().enumerate() {
+            let sha = format!("{:040x}", index + 1);
+            if index == 0 {
+                body.push_str(&pkt(&format!(
+                    "{sha} {reference}\0multi_ack side-band-64k\n"
+                )));
+            } else {
+                body.push_str(&pkt(&format!("{sha} {reference}\n")));
+            }
+        }
+        body.push_str("0000");
+        body
+    }
+
+    #[test]
+    fn github_latest_reads_the_latest_release() {
+        let http = FakeHttp::default().with(
+            "http://fixture/github-api/repos/octo/tool/releases/latest",
+            r#"{"tag_name": "v2.1.0", "name": "Tool 2.1.0"}"#,
+        );
+        let (strategy, latest) = check(
+            &http,
+            "  url \"https://github.com/octo/tool/releases/download/v2.0.0/tool-2.0.0.tar.gz\"",
+        );
+        assert_eq!(strategy, Strategy::GithubLatest);
+        assert_eq!(latest.as_deref(), Some("2.1.0"));
+    }
+
+    #[test]
+    fn git_reads_tags_from_the_ref_advertisement() {
+        let http = FakeHttp::default().with(
+            "http://fixture/github/octo/tool.git/info/refs?service=git-upload-pack",
+            &advertisement(&[
+                "HEAD",
+                "refs/heads/main",
+                "refs/tags/v1.9.0",
+                "refs/tags/v1.10.0",
+                "refs/tags/v1.10.0^{}",
+                "refs/tags/v2.0.0-rc1",
+                "refs/tags/nightly",
+            ]),
+        );
+        let (strategy, latest) = check(
+            &http,
+            "  url \"https://github.com/octo/tool/archive/refs/tags/v1.9.0.tar.gz\"",
+        );
+        assert_eq!(strategy, Strategy::Git);
+        assert_eq!(latest.as_deref(), Some("1.10.0"));
+    }
+
+    #[test]
+    fn git_uses_the_gitlab_endpoint_and_livecheck_regex() {
+        let http = FakeHttp::default().with(
+            "http://fixture/gitlab/group/tool.git/info/refs?service=git-upload-pack",
+            &advertisement(&["refs/tags/release-3.2", "refs/tags/release-3.10"]),
+        );
+        let (strategy, latest) = check(
+            &http,
+            "  url \"https://gitlab.com/group/tool/-/archive/release-3.2/tool-release-3.2.tar.gz\"\n  livecheck do\n    url :stable\n    regex(/^release-(\\d+(?:\\.\\d+)+)$/i)\n  end",
+        );
+        assert_eq!(strategy, Strategy::Git);
+        assert_eq!(latest.as_deref(), Some("3.10"));
+    }
+
+    #[test]
+    fn git_follows_a_self_hosted_remote() {
+        let http = FakeHttp::default().with(
+            "https://git.example.org/tool.git/info/refs?service=git-upload-pack",
+            &advertisement(&["refs/tags/v0.4.0", "refs/tags/v0.5.0"]),
+        );
+        let (strategy, latest) = check(
+            &http,
+            "  url \"https://git.example.org/tool.git\",\n      tag:      \"v0.4.0\",\n      revision: \"0000000000000000000000000000000000000001\"\n  livecheck do\n    url :stable\n    strategy :git\n    regex(/^v?(\\d+(?:\\.\\d+)+)$/i)\n  end",
+        );
+        assert_eq!(strategy, Strategy::Git);
+        assert_eq!(latest.as_deref(), Some("0.5.0"));
+    }
+
+    #[test]
+    fn malformed_ref_advertisements_are_errors() {
+        assert!(advertised_tags("zzzz").is_err());
+        assert!(advertised_tags("0040short").is_err());
+        assert_eq!(advertised_tags("").unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn pypi_reads_the_project_version() {
+        let http = FakeHttp::default().with(
+            "http://fixture/pypi/pypi/tool/json",
+            r#"{"info": {"name": "tool", "version": "4.0.1"}, "releases": {}}"#,
+        );
+        let (strategy, latest) = check(
+            &http,
+            "  url \"https://files.pythonhosted.org/packages/ab/cd/tool-4.0.0.tar.gz\"",
+        );
+        assert_eq!(strategy, Strategy::Pypi);
+        assert_eq!(latest.as_deref(), Some("4.0.1"));
+    }
+
+    #[test]
+    fn npm_reads_the_latest_dist_tag() {
+        let http = FakeHttp::default().with(
+            "http://fixture/npm/@scope/tool/latest",
+            r#"{"name": "@scope/tool", "version": "5.2.0"}"#,
+        );
+        let (strategy, latest) = check(
+            &http,
+            "  url \"https://registry.npmjs.org/@scope/tool/-/tool-5.1.0.tgz\"",
+        );
+        assert_eq!(strategy, Strategy::Npm);
+        assert_eq!(latest.as_deref(), Some("5.2.0"));
+    }
+
+    #[test]
+    fn crate_reads_the_newest_stable_version() {
+        let http = FakeHttp::default().with(
+            "http://fixture/crates/api/v1/crates/tool",
+            r#"{"crate": {"name": "tool", "max_version": "0.9.0-beta.1", "max_stable_version": "0.8.3"}}"#,
+        );
+        let (strategy, latest) = check(
+            &http,
+            "  url \"https://static.crates.io/crates/tool/tool-0.8.0.crate\"",
+        );
+        assert_eq!(strategy, Strategy::Crate);
+        assert_eq!(latest.as_deref(), Some("0.8.3"));
+    }
+
+    #[test]
+    fn sourceforge_reads_file_paths_from_the_feed() {
+        let http = FakeHttp::default().with(
+            "http://fixture/sourceforge/projects/tool/rss",
+            "<rss><item><link>https://sourceforge.net/projects/tool/files/tool/1.4/tool-1.4.tar.gz/download</link>\
+             <media:content url=\"https://sourceforge.net/projects/tool/files/tool/1.4/tool-1.4.tar.gz/download\"/></item>\
+             <item><media:content url=\"https://sourceforge.net/projects/tool/files/tool/1.12/tool-1.12.tar.gz/download\"/></item></rss>",
+        );
+        let (strategy, latest) = check(
+            &http,
+            "  url \"https://downloads.sourceforge.net/project/tool/tool/1.4/tool-1.4.tar.gz\"",
+        );
+        assert_eq!(strategy, Strategy::SourceForge);
+        assert_eq!(latest.as_deref(), Some("1.12"));
+    }
+
+    #[test]
+    fn page_match_applies_the_livecheck_regex() {
+        let http = FakeHttp::default().with(
+            "https://example.org/downloads/",
+            r#"<a href="tool-1.2.tar.gz">tool-1.2</a> <a href="tool-1.10.tar.gz">tool-1.10</a> <a href="tool-2.0-beta.tar.gz">beta</a>"#,
+        );
+        let (strategy, latest) = check(
+            &http,
+            "  url \"https://example.org/downloads/tool-1.2.tar.gz\"\n  livecheck do\n    url \"https://example.org/downloads/\"\n    regex(/href=.*?tool[._-]v?(\\d+(?:\\.\\d+)+(?:-beta)?)\\.t/i)\n  end",
+        );
+        assert_eq!(strategy, Strategy::PageMatch);
+        assert_eq!(latest.as_deref(), Some("1.10"));
+        assert_eq!(*http.requested.borrow(), ["https://example.org/downloads/"]);
+    }
+
+    #[test]
+    fn http_errors_surface() {
+        let http = FakeHttp::default();
+        let checker = Checker::new(&http, endpoints());
+        let source = formula("  url \"https://registry.npmjs.org/tool/-/tool-1.0.0.tgz\"");
+        assert!(checker.latest(&source, Strategy::Npm).is_err());
+    }
+
+    #[test]
+    fn generic_urls_without_a_regex_have_no_strategy() {
+        let http = FakeHttp::default();
+        let checker = Checker::new(&http, endpoints());
+        let source = formula("  url \"https://example.org/downloads/tool-1.2.tar.gz\"");
+        assert_eq!(checker.strategy(&source), None);
+    }
+
+    #[test]
+    fn versions_compare_numerically() {
+        assert_eq!(compare_versions("1.10", "1.9"), Ordering::Greater);
+        assert_eq!(compare_versions("1.0", "1.0.0"), Ordering::Less);
+        assert_eq!(compare_versions("2.0", "2.0"), Ordering::Equal);
+        assert_eq!(
+            newest(vec!["1.2".into(), "1.3rc1".into(), "1.2.1".into()]).as_deref(),
+            Some("1.2.1")
+        );
+    }
+}
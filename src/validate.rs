@@ -0,0 +1,468 @@
+use anyhow::{bail, Result};
+
+const GITHUB_LOGIN_MAX: usize = 39;
+const GITHUB_REPO_MAX: usize = 100;
+
+/// GitHub user and organization names: 1-39 ASCII letters, digits and single
+/// hyphens, not starting or ending with a hyphen.
+pub fn github_login(label: &str, value: &str) -> Result<()> {
+    non_empty(label, value)?;
+
+    if value.len() > GITHUB_LOGIN_MAX {
+        bail!(
+            "{} '{}' is {} characters long; GitHub allows at most {}",
+            label,
+            value,
+            value.len(),
+            GITHUB_LOGIN_MAX
+        );
+    }
+
+    if let Some((index, ch)) = first_invalid(value, |ch| ch.is_ascii_alphanumeric() || ch == '-') {
+        bail!(
+            "{} '{}' contains {} at position {}; only ASCII letters, digits and '-' are allowed",
+            label,
+            value,
+            describe_char(ch),
+            index + 1
+        );
+    }
+
+    if value.starts_with('-') || value.ends_with('-') {
+        bail!("{} '{}' must not start or end with '-'", label, value);
+    }
+
+    if value.contains("--") {
+        bail!("{} '{}' must not contain consecutive hyphens", label, value);
+    }
+
+    Ok(())
+}
+
+/// GitHub repository names: up to 100 ASCII letters, digits, '.', '_' and '-'.
+pub fn github_repo_name(label: &str, value: &str) -> Result<()> {
+    non_empty(label, value)?;
+
+    if value.len() > GITHUB_REPO_MAX {
+        bail!(
+            "{} '{}' is {} characters long; GitHub allows at most {}",
+            label,
+            value,
+            value.len(),
+            GITHUB_REPO_MAX
+        );
+    }
+
+    if let Some((index, ch)) = first_invalid(value, |ch| {
+        ch.is_ascii_alphanumeric() || matches!(ch, '.' | '_' | '-')
+    }) {
+        bail!(
+            "{} '{}' contains {} at position {}; only ASCII letters, digits, '.', '_' and '-' are allowed",
+            label,
+            value,
+            describe_char(ch),
+            index + 1
+        );
+    }
+
+    if value.starts_with('-') {
+        bail!(
+            "{} '{}' must not start with '-' (it would be read as a flag)",
+            label,
+            value
+        );
+    }
+
+    if value == "." || value == ".." {
+        bail!("{} must not be '{}'", label, value);
+    }
+
+    if value.to_ascii_lowercase().ends_with(".git") {
+        bail!("{} '{}' must not end with '.git'", label, value);
+    }
+
+    Ok(())
+}
+
+/// Branch names, following the rules of `git check-ref-format --branch`.
+pub fn branch(value: &str) -> Result<()> {
+    git_ref("branch", value)
+}
+
+/// Tag names; git applies the same rules as for branches.
+pub fn tag(value: &str) -> Result<()> {
+    git_ref("tag", value)
+}
+
+fn git_ref(label: &str, value: &str) -> Result<()> {
+    non_empty(label, value)?;
+
+    if value.starts_with('-') {
+        bail!("{} '{}' must not start with '-'", label, value);
+    }
+
+    if value == "@" || value == "HEAD" {
+        bail!("{} must not be '{}'", label, value);
+    }
+
+    if let Some((index, ch)) = first_invalid(value, |ch| {
+        !ch.is_ascii_control() && !matches!(ch, ' ' | '~' | '^' | ':' | '?' | '*' | '[' | '\\')
+    }) {
+        bail!(
+            "{} '{}' contains {} at position {}; git refs cannot contain spaces, control characters or any of ~ ^ : ? * [ \\",
+            label,
+            value,
+            describe_char(ch),
+            index + 1
+        );
+    }
+
+    if value.starts_with('/') || value.ends_with('/') {
+        bail!("{} '{}' must not start or end with '/'", label, value);
+    }
+
+    if value.contains("//") {
+        bail!("{} '{}' must not contain consecutive slashes", label, value);
+    }
+
+    if value.contains("..") {
+        bail!("{} '{}' must not contain '..'", label, value);
+    }
+
+    if value.contains("@{") {
+        bail!("{} '{}' must not contain '@{{'", label, value);
+    }
+
+    if value.ends_with('.') {
+        bail!("{} '{}' must not end with '.'", label, value);
+    }
+
+    for component in value.split('/') {
+        if component.starts_with('.') {
+            bail!(
+                "{} '{}' has a path component starting with '.' ({})",
+                label,
+                value,
+                component
+            );
+        }
+        if component.ends_with(".lock") {
+            bail!(
+                "{} '{}' has a path component ending with '.lock' ({})",
+                label,
+                value,
+                component
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Homebrew formula names: lowercase letters, digits, '+', '-', '_' and '.',
+/// optionally versioned with a single '@' suffix such as `openssl@3`.
+pub fn formula_name(value: &str) -> Result<()> {
+    non_empty("formula name", value)?;
+
+    if let Some((index, ch)) = first_invalid(value, |ch| {
+        ch.is_ascii_lowercase() || ch.is_ascii_digit() || matches!(ch, '+' | '-' | '_' | '.' | '@')
+    }) {
+        let hint = if ch.is_ascii_uppercase() {
+            format!(
+                "; formula names are lowercase (try '{}')",
+                value.to_ascii_lowercase()
+            )
+        } else {
+            "; only lowercase letters, digits, '+', '-', '_', '.' and '@' are allowed".to_string()
+        };
+        bail!(
+            "formula name '{}' contains {} at position {}{}",
+            value,
+            describe_char(ch),
+            index + 1,
+            hint
+        );
+    }
+
+    if !value.starts_with(|ch: char| ch.is_ascii_alphanumeric()) {
+        bail!(
+            "formula name '{}' must start with a lowercase letter or digit",
+            value
+        );
+    }
+
+    if value.ends_with(['.', '-', '_']) {
+        bail!(
+            "formula name '{}' must not end with '{}'",
+            value,
+            value.chars().last().unwrap_or_default()
+        );
+    }
+
+    if let Some((base, version)) = value.split_once('@') {
+        if base.is_empty() {
+            bail!("formula name '{}' is missing a name before '@'", value);
+        }
+        if version.contains('@') {
+            bail!("formula name '{}' must contain at most one '@'", value);
+        }
+        let valid_version = version.starts_with(|ch: char| ch.is_ascii_digit())
+            && version
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || ch == '.');
+        if !valid_version {
+            bail!(
+                "formula name '{}' has an invalid version suffix '@{}'; expected something like '@3' or '@1.2'",
+                value,
+                version
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn non_empty(label: &str, value: &str) -> Result<()> {
+    if value.is_empty() {
+        bail!("{} is required", label);
+    }
+    Ok(())
+}
+
+fn first_invalid(value: &str, allowed: impl Fn(char) -> bool) -> Option<(usize, char)> {
+    value.chars().enumerate().find(|(_, ch)| !allowed(*ch))
+}
+
+fn describe_char(ch: char) -> String {
+    if ch.is_whitespace() {
+        "whitespace".to_string()
+    } else if ch.is_control() {
+        format!("control character U+{:04X}", ch as u32)
+    } else {
+        format!("'{}'", ch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::process::Command;
+
+    #[test]
+    fn github_logins() {
+        for valid in ["octocat", "a", "Homebrew", "my-org-2", &"a".repeat(39)] {
+            assert!(github_login("owner", valid).is_ok(), "{valid} was rejected");
+        }
+        for invalid in [
+            "",
+            "-octo",
+            "octo-",
+            "oc--to",
+            "octo_cat",
+            "octo.cat",
+            "octo cat",
+            "oktö",
+            &"a".repeat(40),
+        ] {
+            assert!(
+                github_login("owner", invalid).is_err(),
+                "{invalid} was accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn github_repo_names() {
+        for valid in [
+            "homebrew-tools",
+            "tools.rs",
+            "_private",
+            "a",
+            &"r".repeat(100),
+        ] {
+            assert!(
+                github_repo_name("repo", valid).is_ok(),
+                "{valid} was rejected"
+            );
+        }
+        for invalid in [
+            "",
+            ".",
+            "..",
+            "-tools",
+            "tools.git",
+            "tools.GIT",
+            "tools/cli",
+            "tools cli",
+            &"r".repeat(101),
+        ] {
+            assert!(
+                github_repo_name("repo", invalid).is_err(),
+                "{invalid} was accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn git_refs() {
+        for valid in [
+            "main",
+            "feature/x",
+            "v1.2.0",
+            "release-1.x",
+            "a@b",
+            "@a",
+            "é",
+        ] {
+            assert!(branch(valid).is_ok(), "{valid} was rejected");
+            assert!(tag(valid).is_ok(), "{valid} was rejected");
+        }
+        for invalid in [
+            "",
+            "-main",
+            "@",
+            "HEAD",
+            "a..b",
+            "a@{1}",
+            "main.lock",
+            "a/b.lock/c",
+            "a/.hidden",
+            ".hidden",
+            "main.",
+            "/main",
+            "main/",
+            "a//b",
+            "a b",
+            "a~1",
+            "a^",
+            "a:b",
+            "a?",
+            "a*",
+            "a[b",
+            "a\\b",
+            "a\u{7f}b",
+            "a\tb",
+            "a\u{1}b",
+        ] {
+            assert!(branch(invalid).is_err(), "{invalid:?} was accepted");
+            assert!(tag(invalid).is_err(), "{invalid:?} was accepted");
+        }
+    }
+
+    #[test]
+    fn formula_names() {
+        for valid in [
+            "wget",
+            "openssl@3",
+            "python@3.12",
+            "lua@5.1",
+            "gtk+3",
+            "c-ares",
+            "libxml2",
+            "ca-certificates",
+            "node@20",
+            "7zip",
+            "x264",
+            "py3cairo",
+            "php@8.2",
+        ] {
+            assert!(formula_name(valid).is_ok(), "{valid} was rejected");
+        }
+        for invalid in [
+            "",
+            "Wget",
+            "-wget",
+            ".wget",
+            "wget-",
+            "wget.",
+            "wget_",
+            "wget@",
+            "@3",
+            "openssl@three",
+            "openssl@3@4",
+            "openssl@3-beta",
+            "w get",
+            "wget/cli",
+        ] {
+            assert!(formula_name(invalid).is_err(), "{invalid} was accepted");
+        }
+
+        let err = formula_name("Wget").unwrap_err().to_string();
+        assert!(err.contains("try 'wget'"), "{err}");
+    }
+
+    fn git_accepts_branch(value: &str) -> Option<bool> {
+        Command::new("git")
+            .args(["check-ref-format", "--branch", value])
+            .output()
+            .ok()
+            .map(|output| output.status.success())
+    }
+
+    proptest! {
+        #[test]
+        fn valid_logins_are_accepted(login in "[A-Za-z0-9](-?[A-Za-z0-9]){0,18}") {
+            prop_assert!(github_login("owner", &login).is_ok());
+        }
+
+        #[test]
+        fn accepted_logins_follow_github_rules(login in "[a-z0-9_. -]{0,45}") {
+            if github_login("owner", &login).is_ok() {
+                prop_assert!((1..=GITHUB_LOGIN_MAX).contains(&login.len()));
+                prop_assert!(!login.starts_with('-') && !login.ends_with('-'));
+                prop_assert!(!login.contains("--"));
+                prop_assert!(login.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '-'));
+            }
+        }
+
+        #[test]
+        fn accepted_repo_names_follow_github_rules(name in "[a-z./_ -]{0,8}(\\.git)?") {
+            if github_repo_name("repo", &name).is_ok() {
+                prop_assert!(!name.starts_with('-'));
+                prop_assert!(name != "." && name != "..");
+                prop_assert!(!name.ends_with(".git"));
+                prop_assert!(name
+                    .chars()
+                    .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '.' | '_' | '-')));
+            }
+        }
+
+        #[test]
+        fn forbidden_sequences_make_refs_invalid(
+            base in "[a-z][a-z0-9]{0,6}(/[a-z0-9]{1,6}){0,2}",
+            bad in prop::sample::select(vec![
+                "..", "@{", " ", "~", "^", ":", "?", "*", "[", "\\", "\u{1}", "\u{7f}", "//",
+            ]),
+        ) {
+            prop_assert!(branch(&base).is_ok());
+            let value = format!("{}{}x", base, bad);
+            prop_assert!(branch(&value).is_err(), "{:?}", value);
+            let value = format!("-{}", base);
+            prop_assert!(branch(&value).is_err(), "{:?}", value);
+            let value = format!("{}.lock", base);
+            prop_assert!(branch(&value).is_err(), "{:?}", value);
+            let value = format!("{}/.x", base);
+            prop_assert!(branch(&value).is_err(), "{:?}", value);
+        }
+
+        #[test]
+        fn refs_match_git_check_ref_format(value in "[ab./@{~ -]{1,6}") {
+            // A ref named `@` is forbidden by git's ref rules, though
+            // `--branch` resolves it to the current branch instead.
+            prop_assume!(value != "@");
+            if let Some(git) = git_accepts_branch(&value) {
+                prop_assert_eq!(branch(&value).is_ok(), git, "{:?}", value);
+            }
+        }
+
+        #[test]
+        fn accepted_formula_names_are_lowercase(name in "[a-zA-Z0-9+._@-]{0,10}") {
+            if formula_name(&name).is_ok() {
+                prop_assert!(!name.chars().any(|ch| ch.is_ascii_uppercase()));
+                prop_assert!(name.starts_with(|ch: char| ch.is_ascii_alphanumeric()));
+                prop_assert!(name.matches('@').count() <= 1);
+            }
+        }
+    }
+}
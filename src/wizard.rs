@@ -0,0 +1,349 @@
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::process::Command;
+
+use crate::binary_formula;
+use crate::exec::{self, CommandExt};
+use crate::inputs::{
+    self, Completions, FormulaMode, InputOverrides, Inputs, SourceKind, TestStrategy, Visibility,
+};
+use crate::plan::shell_quote;
+use crate::source_url;
+use crate::state::APP_NAME;
+use crate::validate;
+
+/// The wizard only runs when a person can answer it.
+pub fn available() -> bool {
+    io::stdin().is_terminal()
+}
+
+/// Prompts for every input not already given on the command line, then prints
+/// the equivalent non-interactive command.
+pub fn prompt_inputs(given: InputOverrides) -> Result<Inputs> {
+    println!("Missing required inputs; starting interactive setup (Ctrl-C to abort).\n");
+
+    let owner = match given.owner {
+        Some(owner) => owner,
+        None => prompt_owner()?,
+    };
+    let tap = match given.tap {
+        Some(tap) => tap,
+        None => prompt_validated(
+            "Tap short name (without homebrew-)",
+            None,
+            inputs::normalize_tap,
+        )?,
+    };
+    let visibility = match given.visibility {
+        Some(visibility) => visibility,
+        None => prompt_choice("Visibility", Visibility::Public)?,
+    };
+    let branch = match given.branch {
+        Some(branch) => branch,
+        None => prompt_validated("Default branch", Some("main"), inputs::normalize_branch)?,
+    };
+    let formula_mode = match given.formula_mode {
+        Some(mode) => mode,
+        None => prompt_choice("Formula mode", FormulaMode::Stub)?,
+    };
+
+    let mut formula_url = given.formula_url;
+    let mut formula_name = given.formula_name;
+    if formula_mode != FormulaMode::Stub {
+        if formula_url.is_none() {
+            let label = match formula_mode {
+                FormulaMode::GithubRelease => "GitHub repository or release URL",
+                _ => "Source tarball URL",
+            };
+            formula_url = Some(prompt_validated(label, None, |value| {
+                let trimmed = value.trim();
+                if trimmed.is_empty() {
+                    bail!("formula-url is required for this formula mode");
+                }
+                if formula_mode == FormulaMode::GithubRelease {
+                    binary_formula::release_target(trimmed)?;
+                }
+                Ok(trimmed.to_string())
+            })?);
+        }
+        if formula_name.is_none() {
+            let derived = formula_url.as_deref().and_then(|url| match formula_mode {
+                FormulaMode::GithubRelease => binary_formula::release_target(url)
+                    .ok()
+                    .map(|(repo, _)| binary_formula::release_formula_name(&repo)),
+                _ => source_url::analyze(url).formula_name(),
+            });
+            formula_name = Some(prompt_validated(
+                "Formula name",
+                derived.as_deref(),
+                inputs::normalize_formula_name,
+            )?);
+        }
+    }
+
+    let source_kind = match given.source_kind {
+        Some(kind) => Some(kind),
+        None if formula_mode == FormulaMode::Stub => {
+            Some(prompt_choice("Source kind", SourceKind::Tarball)?)
+        }
+        None => None,
+    };
+    let mut source_tag = given.source_tag;
+    let mut head_branch = given.head_branch;
+    if matches!(source_kind, Some(SourceKind::GitTag | SourceKind::Head)) {
+        if formula_url.is_none() {
+            formula_url = Some(prompt_validated("Git repository URL", None, |value| {
+                let trimmed = value.trim();
+                if trimmed.is_empty() {
+                    bail!("formula-url is required for this source kind");
+                }
+                Ok(trimmed.to_string())
+            })?);
+        }
+        if source_kind == Some(SourceKind::GitTag) && source_tag.is_none() {
+            source_tag = Some(prompt_validated("Tag", None, |value| {
+                let trimmed = value.trim();
+                validate::tag(trimmed)?;
+                Ok(trimmed.to_string())
+            })?);
+        }
+        if source_kind == Some(SourceKind::Head) && head_branch.is_none() {
+            head_branch = Some(prompt_validated(
+                "Head branch",
+                Some("main"),
+                inputs::normalize_branch,
+            )?);
+        }
+    }
+
+    let mut python_lock = given.python_lock;
+    if formula_mode == FormulaMode::Python && python_lock.is_none() {
+        let answer = read_answer("Requirements lock file (blank for no resources)", None)?;
+        python_lock = Some(answer).filter(|answer| !answer.is_empty());
+    }
+
+    let inputs = Inputs::new(InputOverrides {
+        owner: Some(owner),
+        tap: Some(tap),
+        repo_name: given.repo_name,
+        visibility: Some(visibility),
+        branch: Some(branch),
+        formula_mode: Some(formula_mode),
+        formula_url,
+        formula_name,
+        artifacts: given.artifacts,
+        python_lock,
+        python: given.python,
+        source_kind,
+        source_tag,
+        head_branch,
+        depends_on: given.depends_on,
+        service_run: given.service_run,
+        service_keep_alive: given.service_keep_alive,
+        service_log_path: given.service_log_path,
+        caveats: given.caveats,
+        completions: given.completions,
+        manpage: given.manpage,
+        test_strategy: given.test_strategy,
+        test_command: given.test_command,
+        test_expect: given.test_expect,
+        license: given.license,
+    })?;
+
+    println!(
+        "\nEquivalent command for scripts:\n  {}\n",
+        command_line(&inputs)
+    );
+    Ok(inputs)
+}
+
+fn prompt_owner() -> Result<String> {
+    let login = gh_lines(&["api", "user", "--jq", ".login"])
+        .into_iter()
+        .next();
+    let orgs = gh_lines(&["api", "user/orgs", "--jq", ".[].login"]);
+    if !orgs.is_empty() {
+        println!("Your organizations: {}", orgs.join(", "));
+    }
+
+    prompt_validated(
+        "GitHub owner or org",
+        login.as_deref(),
+        inputs::normalize_owner,
+    )
+}
+
+/// Runs a `gh` query and returns its non-empty output lines; any failure
+/// (not logged in, offline, ...) just means there is no suggestion.
+fn gh_lines(args: &[&str]) -> Vec<String> {
+    let output = match Command::new("gh")
+        .args(args)
+        .output_within(exec::QUERY_TIMEOUT)
+    {
+        Ok(output) if output.status.success() => output,
+        _ => return Vec::new(),
+    };
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Asks until `validate` accepts the answer; an empty answer takes `default`.
+fn prompt_validated(
+    label: &str,
+    default: Option<&str>,
+    validate: impl Fn(String) -> Result<String>,
+) -> Result<String> {
+    loop {
+        let answer = read_answer(label, default)?;
+        match validate(answer) {
+            Ok(value) => return Ok(value),
+            Err(err) => println!("  {err:#}"),
+        }
+    }
+}
+
+fn prompt_choice<T: ValueEnum + Copy>(label: &str, default: T) -> Result<T> {
+    let names: Vec<String> = T::value_variants()
+        .iter()
+        .filter_map(|variant| variant.to_possible_value())
+        .map(|value| value.get_name().to_string())
+        .collect();
+    let default_name = default
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default();
+
+    loop {
+        let answer = read_answer(
+            &format!("{} ({})", label, names.join("/")),
+            Some(&default_name),
+        )?;
+        match T::from_str(&answer, true) {
+            Ok(value) => return Ok(value),
+            Err(_) => println!("  choose one of: {}", names.join(", ")),
+        }
+    }
+}
+
+fn read_answer(label: &str, default: Option<&str>) -> Result<String> {
+    match default {
+        Some(default) => print!("{label} [{default}]: "),
+        None => print!("{label}: "),
+    }
+    io::stdout().flush()?;
+
+    let mut line = String::new();
+    let read = io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .context("failed to read answer")?;
+    if read == 0 {
+        bail!("input closed before setup was complete");
+    }
+
+    let answer = line.trim();
+    Ok(match (answer.is_empty(), default) {
+        (true, Some(default)) => default.to_string(),
+        _ => answer.to_string(),
+    })
+}
+
+fn command_line(inputs: &Inputs) -> String {
+    let mut parts = vec![
+        APP_NAME.to_string(),
+        format!("--owner {}", shell_quote(&inputs.owner)),
+        format!("--tap {}", shell_quote(&inputs.tap)),
+    ];
+    if inputs.repo_name != format!("homebrew-{}", inputs.tap) {
+        parts.push(format!("--repo-name {}", shell_quote(&inputs.repo_name)));
+    }
+    parts.push(format!(
+        "--visibility {}",
+        inputs.field_value(inputs::InputField::Visibility)
+    ));
+    parts.push(format!("--branch {}", shell_quote(&inputs.branch)));
+    parts.push(format!(
+        "--formula-mode {}",
+        inputs.field_value(inputs::InputField::FormulaMode)
+    ));
+    if let Some(url) = &inputs.formula_url {
+        parts.push(format!("--formula-url {}", shell_quote(url)));
+    }
+    if let Some(name) = &inputs.formula_name {
+        parts.push(format!("--formula-name {}", shell_quote(name)));
+    }
+    if inputs.source_kind != SourceKind::Tarball {
+        parts.push(format!(
+            "--source-kind {}",
+            inputs.field_value(inputs::InputField::SourceKind)
+        ));
+    }
+    if let Some(tag) = &inputs.source_tag {
+        parts.push(format!("--source-tag {}", shell_quote(tag)));
+    }
+    if let Some(branch) = &inputs.head_branch {
+        parts.push(format!("--head-branch {}", shell_quote(branch)));
+    }
+    for dependency in &inputs.depends_on {
+        parts.push(format!(
+            "--depends-on {}",
+            shell_quote(&dependency.to_string())
+        ));
+    }
+    if let Some(service) = &inputs.service {
+        parts.push(format!(
+            "--service-run {}",
+            shell_quote(&service.run.join(" "))
+        ));
+        if service.keep_alive {
+            parts.push("--service-keep-alive".to_string());
+        }
+        if let Some(path) = &service.log_path {
+            parts.push(format!("--service-log-path {}", shell_quote(path)));
+        }
+    }
+    if let Some(caveats) = &inputs.caveats {
+        parts.push(format!("--caveats {}", shell_quote(caveats)));
+    }
+    if inputs.completions != Completions::None {
+        parts.push(format!(
+            "--completions {}",
+            inputs.field_value(inputs::InputField::Completions)
+        ));
+    }
+    if let Some(path) = &inputs.manpage {
+        parts.push(format!("--manpage {}", shell_quote(path)));
+    }
+    if inputs.test_strategy != TestStrategy::Cli {
+        parts.push(format!(
+            "--test-strategy {}",
+            inputs.field_value(inputs::InputField::TestStrategy)
+        ));
+    }
+    if let Some(command) = &inputs.test_command {
+        parts.push(format!("--test-command {}", shell_quote(command)));
+    }
+    if let Some(expect) = &inputs.test_expect {
+        parts.push(format!("--test-expect {}", shell_quote(expect)));
+    }
+    if let Some(license) = &inputs.license {
+        parts.push(format!("--license {}", shell_quote(&license.to_string())));
+    }
+    if let Some(lock) = &inputs.python_lock {
+        parts.push(format!("--python-lock {}", shell_quote(lock)));
+    }
+    if let Some(python) = &inputs.python {
+        parts.push(format!("--python {}", shell_quote(python)));
+    }
+    for artifact in &inputs.artifacts {
+        parts.push(format!("--artifact {}", shell_quote(&artifact.to_string())));
+    }
+
+    parts.join(" ")
+}